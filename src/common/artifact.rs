@@ -0,0 +1,207 @@
+use super::model::{build_classifier, ModelConfig, TextClassifier};
+use super::tokenize::TokenizerConfig;
+use super::vocabulary::Vocabulary;
+use super::PREDICTION_THRESHOLD;
+use anyhow::{Context, Error};
+use candle_core::DType;
+use candle_nn::{VarBuilder, VarMap};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Write},
+};
+
+/// Information about the training run that produced a model.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TrainingMetadata {
+    /// Unix timestamp, in seconds, of the end of the training run.
+    pub trained_at: u64,
+    pub n_train_samples: usize,
+    /// The samples held out of the training data to select the best epoch and calibrate the thresholds.
+    pub n_validation_samples: usize,
+    pub n_test_samples: usize,
+    pub epochs_trained: u32,
+    /// The F1 score of the best epoch on the validation data.
+    pub best_validation_f1: f32,
+    /// The F1 score of the best epoch on the test data, with the default threshold.
+    pub best_test_f1: f32,
+    /// The F1 score of the same model on the test data when using the calibrated thresholds.
+    pub calibrated_test_f1: f32,
+    pub learning_rate: f64,
+    pub batch_size: usize,
+}
+
+/// Everything needed to rebuild a trained model, stored next to its weights.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModelBundle {
+    pub model: ModelConfig,
+    pub tokenizer: TokenizerConfig,
+    /// The class names, ordered by their index in the model outputs.
+    pub labels: Vec<String>,
+    /// The decision threshold of every class, ordered like `labels`. When empty, the global
+    /// `PREDICTION_THRESHOLD` applies to every class.
+    #[serde(default)]
+    pub thresholds: Vec<f32>,
+    pub training: TrainingMetadata,
+}
+
+impl ModelBundle {
+    /// The decision threshold of every class, falling back to `PREDICTION_THRESHOLD` when the
+    /// thresholds were not calibrated.
+    pub fn class_thresholds(&self) -> Vec<f32> {
+        if self.thresholds.is_empty() {
+            vec![PREDICTION_THRESHOLD; self.labels.len()]
+        } else {
+            self.thresholds.clone()
+        }
+    }
+
+    /// Check that the vocabulary and the label mapping loaded next to the model are the ones it was trained with.
+    ///
+    /// # Arguments
+    ///
+    /// * `vocabulary` - A reference to the loaded `Vocabulary`.
+    /// * `index_to_class` - A reference to the loaded index-to-class mapping.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error describing the first mismatch found.
+    pub fn validate(
+        &self,
+        vocabulary: &Vocabulary,
+        index_to_class: &HashMap<u32, String>,
+    ) -> Result<(), Error> {
+        if self.model.n_classes != self.labels.len() {
+            anyhow::bail!(
+                "Model config has n_classes = {} but lists {} labels",
+                self.model.n_classes,
+                self.labels.len()
+            );
+        }
+
+        if !self.thresholds.is_empty() && self.thresholds.len() != self.labels.len() {
+            anyhow::bail!(
+                "Model config has {} thresholds for {} labels",
+                self.thresholds.len(),
+                self.labels.len()
+            );
+        }
+
+        let labels: Vec<Option<&String>> = (0..self.labels.len() as u32)
+            .map(|index| index_to_class.get(&index))
+            .collect();
+        if index_to_class.len() != self.labels.len()
+            || labels
+                .iter()
+                .zip(self.labels.iter())
+                .any(|(label, expected)| *label != Some(expected))
+        {
+            anyhow::bail!(
+                "Index to class mapping {:?} does not match the model labels {:?}",
+                index_to_class,
+                self.labels
+            );
+        }
+
+        if vocabulary.tokenizer != self.tokenizer {
+            anyhow::bail!(
+                "Vocabulary tokenizer settings {:?} do not match the model tokenizer settings {:?}",
+                vocabulary.tokenizer,
+                self.tokenizer
+            );
+        }
+
+        // A vocabulary of another training run usually has another size
+        let n_indices = vocabulary.n_indices();
+        if n_indices != self.model.vocab_size {
+            anyhow::bail!(
+                "Vocabulary has {} entries but the model embedding table has {}",
+                n_indices,
+                self.model.vocab_size
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Store a model bundle in a JSON file.
+///
+/// # Arguments
+///
+/// * `bundle` - A reference to the `ModelBundle` to be stored.
+/// * `file_path` - A string containing the path to the JSON file where the bundle will be stored.
+///
+/// # Errors
+///
+/// This function can return an error if there are issues with file creation, JSON serialization, or file writing.
+///
+/// # Returns
+///
+/// This function returns `Result<(), Error>`, where `()` indicates success and `Error` represents any encountered errors.
+pub fn store_model_bundle(bundle: &ModelBundle, file_path: &str) -> Result<(), Error> {
+    let mut file = File::create(file_path)?;
+    file.write_all(serde_json::to_string_pretty(bundle)?.as_bytes())?;
+    Ok(())
+}
+
+/// Load a model bundle from a JSON file.
+///
+/// # Arguments
+///
+/// * `file_path` - A string containing the path to the JSON file from which the bundle will be loaded.
+///
+/// # Errors
+///
+/// This function can return an error if there are issues with file opening, reading, or JSON deserialization.
+///
+/// # Returns
+///
+/// This function returns a `Result<ModelBundle, Error>`, where `ModelBundle` represents the loaded bundle on success, and `Error` represents any encountered errors.
+pub fn load_model_bundle(file_path: &str) -> Result<ModelBundle, Error> {
+    let mut file = File::open(file_path)
+        .with_context(|| format!("Failed to open model config {}", file_path))?;
+    let mut json_data = String::new();
+
+    file.read_to_string(&mut json_data)?;
+
+    let bundle: ModelBundle = serde_json::from_str(&json_data)
+        .with_context(|| format!("Failed to parse model config {}", file_path))?;
+
+    Ok(bundle)
+}
+
+/// Build the model of the architecture set in its configuration and load its trained weights.
+///
+/// # Arguments
+///
+/// * `weights_path` - A string containing the path to the safetensors file holding the weights.
+/// * `config` - A reference to the `ModelConfig` the weights were trained with.
+///
+/// # Errors
+///
+/// This function returns an error if the weights cannot be read or if their shapes do not match the configuration.
+///
+/// # Returns
+///
+/// This function returns a `Result<Box<dyn TextClassifier>, Error>` with the loaded model on success.
+pub fn load_model(
+    weights_path: &str,
+    config: &ModelConfig,
+) -> Result<Box<dyn TextClassifier>, Error> {
+    // The variables have to be created by the model before the VarMap can fill them from the file.
+    let mut varmap = VarMap::new();
+    let vs = VarBuilder::from_varmap(&varmap, DType::F32, &config.device);
+    let model = build_classifier(&vs, config)?;
+
+    varmap.load(weights_path).with_context(|| {
+        format!(
+            "Weights in {} do not match the model configuration {:?}",
+            weights_path, config
+        )
+    })?;
+
+    Ok(model)
+}
@@ -1,5 +1,9 @@
+use super::preprocess::pad_vector;
+use super::tokenize::Tokenizer;
+use super::vocabulary::SpecialTokens;
 use super::MultiHotEncodeError;
 use anyhow::Error;
+use candle_core::{Device, Tensor};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
@@ -7,31 +11,48 @@ use std::{
     io::{Read, Write},
 };
 
+pub const PAD_TOKEN: &str = "<PAD>";
+pub const UNK_TOKEN: &str = "<UNK>";
+pub const BOS_TOKEN: &str = "<BOS>";
+pub const EOS_TOKEN: &str = "<EOS>";
+
+/// Index of the padding token. Positions holding this index are ignored by the model.
+pub const PAD_INDEX: u32 = 0;
+/// Index of the unknown token, used for any word missing from the vocabulary.
+pub const UNK_INDEX: u32 = 1;
+
 /// Creates a mapping from vocabulary words to their corresponding indices.
 ///
 /// This function takes a reference to a vector of strings `vocabulary` and creates a `HashMap`
 /// where each unique word in the vocabulary is associated with its index (a `u32` value). The
 /// resulting mapping is used for tasks such as converting text data into sequences of indices.
 ///
-/// The special token "<UNK>" is inserted at index 0 in the mapping to represent any unknown words.
-/// When using this mapping to convert words to indices, if a word is not found in the vocabulary,
-/// it is mapped to the index of "<UNK>" (index 0).
+/// The special tokens are inserted first: "<PAD>" at `PAD_INDEX` (0), "<UNK>" at `UNK_INDEX` (1),
+/// followed by the optional "<BOS>" and "<EOS>" tokens. The vocabulary words take the indices after
+/// them. When using this mapping to convert words to indices, if a word is not found in the
+/// vocabulary, it is mapped to the index of "<UNK>".
 ///
 /// # Arguments
 ///
 /// * `vocabulary`: A reference to a vector of strings representing the vocabulary.
+/// * `special_tokens`: The reserved tokens placed before the vocabulary words.
 ///
 /// # Returns
 ///
 /// A `HashMap` mapping words to their corresponding indices.
-pub fn create_vocabulary_to_index_mapping(vocabulary: &Vec<String>) -> HashMap<String, u32> {
+pub fn create_vocabulary_to_index_mapping(
+    vocabulary: &[String],
+    special_tokens: &SpecialTokens,
+) -> HashMap<String, u32> {
     let mut vocab_to_index = HashMap::<String, u32>::new();
 
-    // Special token for any unknown words. In map_to_indices, unwrap_or(0) is used to map any unknown words to this token index.
-    vocab_to_index.insert("<UNK>".to_string(), 0);
+    let reserved = special_tokens.tokens();
+    for (index, token) in reserved.iter().enumerate() {
+        vocab_to_index.insert(token.to_string(), index as u32);
+    }
 
     for (index, word) in vocabulary.iter().enumerate() {
-        vocab_to_index.insert(word.clone(), index as u32 + 1);
+        vocab_to_index.insert(word.clone(), (index + reserved.len()) as u32);
     }
 
     vocab_to_index
@@ -41,8 +62,8 @@ pub fn create_vocabulary_to_index_mapping(vocabulary: &Vec<String>) -> HashMap<S
 ///
 /// This function takes a vector of strings `words` and a reference to a mapping `mapping`, which
 /// associates words with their corresponding indices. It maps each word in the input vector to its
-/// index using the provided mapping. If a word is not found in the mapping, it is mapped to 0 by
-/// default.
+/// index using the provided mapping. If a word is not found in the mapping, it is mapped to
+/// `UNK_INDEX`.
 ///
 /// # Arguments
 ///
@@ -55,10 +76,111 @@ pub fn create_vocabulary_to_index_mapping(vocabulary: &Vec<String>) -> HashMap<S
 pub fn map_to_indices(words: Vec<String>, mapping: &HashMap<String, u32>) -> Vec<u32> {
     words
         .iter()
-        .map(|word| mapping.get(word).copied().unwrap_or(0))
+        .map(|word| mapping.get(word).copied().unwrap_or(UNK_INDEX))
         .collect()
 }
 
+/// The indices of the "<BOS>" and "<EOS>" markers wrapping every sequence, when they are enabled.
+fn sequence_markers(
+    mapping: &HashMap<String, u32>,
+    special_tokens: &SpecialTokens,
+) -> (Option<u32>, Option<u32>) {
+    let index =
+        |token: &Option<String>| token.as_ref().and_then(|token| mapping.get(token)).copied();
+    (index(&special_tokens.bos), index(&special_tokens.eos))
+}
+
+/// The maximum number of words kept by `encode_sequence`, the rest of the sequence being taken by
+/// the "<BOS>"/"<EOS>" markers.
+///
+/// # Arguments
+///
+/// * `mapping`: A reference to a `HashMap` mapping words to their corresponding indices.
+/// * `special_tokens`: The reserved tokens the mapping was created with.
+/// * `max_seq_len`: The length of the encoded sequences.
+pub fn max_sequence_words(
+    mapping: &HashMap<String, u32>,
+    special_tokens: &SpecialTokens,
+    max_seq_len: usize,
+) -> usize {
+    let (bos, eos) = sequence_markers(mapping, special_tokens);
+    max_seq_len.saturating_sub(bos.iter().count() + eos.iter().count())
+}
+
+/// Converts a tokenized sentence into a fixed-length sequence of indices.
+///
+/// The words are mapped to indices, wrapped in the "<BOS>"/"<EOS>" markers when these are
+/// enabled, and padded with `PAD_INDEX` (or truncated) to `max_seq_len`. Truncation only drops
+/// words, so the markers are kept even for long sentences.
+///
+/// # Arguments
+///
+/// * `words`: A vector of strings representing the tokens of a sentence.
+/// * `mapping`: A reference to a `HashMap` mapping words to their corresponding indices.
+/// * `special_tokens`: The reserved tokens the mapping was created with.
+/// * `max_seq_len`: The length of the returned sequence.
+///
+/// # Returns
+///
+/// A vector of `max_seq_len` indices.
+pub fn encode_sequence(
+    words: Vec<String>,
+    mapping: &HashMap<String, u32>,
+    special_tokens: &SpecialTokens,
+    max_seq_len: usize,
+) -> Vec<u32> {
+    let (bos, eos) = sequence_markers(mapping, special_tokens);
+    let max_words = max_sequence_words(mapping, special_tokens, max_seq_len);
+
+    let mut indices: Vec<u32> = bos.into_iter().collect();
+    indices.extend(map_to_indices(words, mapping).into_iter().take(max_words));
+    indices.extend(eos);
+
+    pad_vector(indices, max_seq_len, PAD_INDEX)
+}
+
+/// Tokenizes and encodes a batch of texts into a tensor of token indices.
+///
+/// This is the single encoding path shared by training and inference, so that a headline is
+/// turned into exactly the same indices in both.
+///
+/// # Arguments
+///
+/// * `texts`: The texts to be encoded.
+/// * `tokenizer`: The tokenizer the vocabulary was built with.
+/// * `mapping`: A reference to a `HashMap` mapping words to their corresponding indices.
+/// * `special_tokens`: The reserved tokens the mapping was created with.
+/// * `max_seq_len`: The length every sequence is padded or truncated to.
+/// * `device`: The device the tensor is created on.
+///
+/// # Errors
+///
+/// This function can return an error if the tensor cannot be created.
+///
+/// # Returns
+///
+/// A `u32` tensor of shape `(n_texts, max_seq_len)`.
+pub fn encode_texts(
+    texts: &[String],
+    tokenizer: &dyn Tokenizer,
+    mapping: &HashMap<String, u32>,
+    special_tokens: &SpecialTokens,
+    max_seq_len: usize,
+    device: &Device,
+) -> Result<Tensor, Error> {
+    let indices: Vec<u32> = texts
+        .iter()
+        .flat_map(|text| {
+            let words = tokenizer.tokenize(text);
+            encode_sequence(words, mapping, special_tokens, max_seq_len)
+        })
+        .collect();
+
+    let tensor = Tensor::from_vec(indices, (texts.len(), max_seq_len), device)?;
+
+    Ok(tensor)
+}
+
 /// Creates mappings between class labels and their corresponding indices.
 ///
 /// This function takes a reference to a vector of class labels `labels` and creates two mappings:
@@ -79,7 +201,7 @@ pub fn map_to_indices(words: Vec<String>, mapping: &HashMap<String, u32>) -> Vec
 ///   - The first `HashMap` maps class labels (strings) to their corresponding indices (u32).
 ///   - The second `HashMap` maps indices to their corresponding class labels.
 pub fn create_class_mapping_from_labels(
-    labels: &Vec<String>,
+    labels: &[String],
 ) -> (HashMap<String, u32>, HashMap<u32, String>) {
     let mut class_to_index: HashMap<String, u32> = HashMap::new();
     let mut index_to_class: HashMap<u32, String> = HashMap::new();
@@ -87,7 +209,7 @@ pub fn create_class_mapping_from_labels(
     let mut n_classes = 0;
 
     for word in labels.iter() {
-        if word == "" {
+        if word.is_empty() {
             continue;
         }
         let labels: Vec<&str> = word.split('|').collect();
@@ -136,7 +258,7 @@ pub fn multi_hot_encode(
     for label in labels {
         let mut label_encodings = vec![0u32; n_classes];
         log::debug!("Label: {:?}", label);
-        if label == "" {
+        if label.is_empty() {
             log::debug!("Encoding: {:?}", label_encodings);
             all_encodings.append(&mut label_encodings);
             // Skip empty labels
@@ -182,7 +304,6 @@ struct IndexToClassMapping {
 /// # Returns
 ///
 /// This function returns `Result<(), Error>`, where `()` indicates success and `Error` represents any encountered errors.
-
 pub fn store_index_to_class_mapping(
     index_to_class: &HashMap<u32, String>,
     file_path: &str,
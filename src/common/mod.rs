@@ -1,15 +1,19 @@
+pub mod artifact;
 pub mod encode;
 mod exception;
 pub mod model;
 pub mod paths;
 pub mod preprocess;
+pub mod tokenize;
 pub mod vocabulary;
 
+pub use artifact::*;
 pub use encode::*;
 use exception::*;
 pub use model::*;
 pub use paths::*;
 pub use preprocess::*;
+pub use tokenize::*;
 pub use vocabulary::*;
 
 pub const PREDICTION_THRESHOLD: f32 = 0.5;
@@ -0,0 +1,112 @@
+use candle_core::{Result, Tensor, D};
+use candle_nn::{
+    conv1d, embedding, linear, Conv1d, Conv1dConfig, Dropout, Embedding, Linear, Module, VarBuilder,
+};
+
+use super::{classify, dropout, embed, n_elements, ModelConfig, TextClassifier};
+
+/// The `cnn` architecture: convolutions of several widths over the embeddings, max-pooled over
+/// time (Kim, 2014).
+#[derive(Debug)]
+pub struct CnnClassifier {
+    embedding: Embedding,
+    /// The convolutions, with the width of their kernel.
+    convolutions: Vec<(usize, Conv1d)>,
+    dropout: Dropout,
+    classifier: Linear,
+    config: ModelConfig,
+}
+
+impl CnnClassifier {
+    /// Create a new instance of a model using the provided VarBuilder and ModelConfig.
+    ///
+    /// # Arguments
+    ///
+    /// * `vb` - A reference to a VarBuilder used for creating variables.
+    /// * `config` - A reference to a ModelConfig containing configuration parameters for the model.
+    ///
+    /// # Errors
+    ///
+    /// This function can return an error if the kernel sizes or the dropout are invalid, or if there are issues with creating the layers using the VarBuilder.
+    pub fn new(vb: &VarBuilder, config: &ModelConfig) -> Result<Self> {
+        if config.kernel_sizes.is_empty() || config.kernel_sizes.contains(&0) {
+            candle_core::bail!(
+                "The cnn architecture needs kernel sizes of at least 1, got {:?}",
+                config.kernel_sizes
+            );
+        }
+
+        let convolutions = config
+            .kernel_sizes
+            .iter()
+            .enumerate()
+            .map(|(index, &kernel_size)| {
+                let convolution = conv1d(
+                    config.embedding_size,
+                    config.n_filters,
+                    kernel_size,
+                    Conv1dConfig::default(),
+                    vb.pp("convolutions").pp(index),
+                )?;
+                Ok((kernel_size, convolution))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let n_features = config.n_filters * config.kernel_sizes.len();
+
+        Ok(Self {
+            embedding: embedding(config.vocab_size, config.embedding_size, vb.pp("embedding"))?,
+            convolutions,
+            dropout: dropout(config)?,
+            classifier: linear(n_features, config.n_classes, vb.pp("classifier"))?,
+            config: config.clone(),
+        })
+    }
+}
+
+impl TextClassifier for CnnClassifier {
+    fn forward_t(&self, input_ids: &Tensor, mask: &Tensor, train: bool) -> Result<Tensor> {
+        let (embeddings, mask) = embed(&self.embedding, input_ids, mask)?;
+
+        // Convolve over time, with the embedding dimensions as channels
+        let channels = embeddings
+            .broadcast_mul(&mask)?
+            .transpose(1, 2)?
+            .contiguous()?;
+        let positions = mask.transpose(1, 2)?;
+
+        let pooled = self
+            .convolutions
+            .iter()
+            .map(|(kernel_size, convolution)| {
+                // Pad on the right so that every token starts a window, even in texts shorter
+                // than the kernel.
+                let windows = channels.pad_with_zeros(D::Minus1, 0, kernel_size - 1)?;
+                // The ReLU outputs are not negative, so zeroing the windows starting on padding
+                // leaves them out of the max.
+                convolution
+                    .forward(&windows)?
+                    .relu()?
+                    .broadcast_mul(&positions)?
+                    .max(D::Minus1)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let features = self.dropout.forward(&Tensor::cat(&pooled, 1)?, train)?;
+        classify(&self.classifier, &features)
+    }
+
+    fn config(&self) -> &ModelConfig {
+        &self.config
+    }
+
+    fn n_parameters(&self) -> usize {
+        let convolutions: usize = self
+            .convolutions
+            .iter()
+            .map(|(_, convolution)| n_elements(convolution.weight(), convolution.bias()))
+            .sum();
+        self.embedding.embeddings().elem_count()
+            + convolutions
+            + n_elements(self.classifier.weight(), self.classifier.bias())
+    }
+}
@@ -0,0 +1,63 @@
+use candle_core::{Result, Tensor};
+use candle_nn::{embedding, linear, Embedding, Linear, Module, VarBuilder};
+
+use super::{classify, embed, masked_mean, n_elements, ModelConfig, TextClassifier};
+
+/// The `mean_pooling` architecture: the mean of the embeddings of the tokens, followed by two
+/// linear layers.
+#[derive(Debug)]
+pub struct MeanPoolingClassifier {
+    embedding: Embedding,
+    fully_connected: Linear,
+    classifier: Linear,
+    config: ModelConfig,
+}
+
+impl MeanPoolingClassifier {
+    /// Create a new instance of a model using the provided VarBuilder and ModelConfig.
+    ///
+    /// # Arguments
+    ///
+    /// * `vb` - A reference to a VarBuilder used for creating variables.
+    /// * `config` - A reference to a ModelConfig containing configuration parameters for the model.
+    ///
+    /// # Errors
+    ///
+    /// This function can return an error if there are issues with creating the layers using the VarBuilder.
+    pub fn new(vb: &VarBuilder, config: &ModelConfig) -> Result<Self> {
+        Ok(Self {
+            embedding: embedding(config.vocab_size, config.embedding_size, vb.pp("embedding"))?,
+            fully_connected: linear(config.embedding_size, config.hidden_size, vb.pp("linear"))?,
+            classifier: linear(config.hidden_size, config.n_classes, vb.pp("classifier"))?,
+            config: config.clone(),
+        })
+    }
+}
+
+impl TextClassifier for MeanPoolingClassifier {
+    fn forward_t(&self, input_ids: &Tensor, mask: &Tensor, _train: bool) -> Result<Tensor> {
+        let (embeddings, mask) = embed(&self.embedding, input_ids, mask)?;
+
+        let mean_embedding = masked_mean(&embeddings, &mask)?;
+        log::debug!(
+            "Mean embedding - Shape {:?}. Values {:?}",
+            mean_embedding.shape(),
+            mean_embedding.get(0)
+        );
+
+        classify(
+            &self.classifier,
+            &self.fully_connected.forward(&mean_embedding)?,
+        )
+    }
+
+    fn config(&self) -> &ModelConfig {
+        &self.config
+    }
+
+    fn n_parameters(&self) -> usize {
+        self.embedding.embeddings().elem_count()
+            + n_elements(self.fully_connected.weight(), self.fully_connected.bias())
+            + n_elements(self.classifier.weight(), self.classifier.bias())
+    }
+}
@@ -0,0 +1,219 @@
+mod cnn;
+mod mean_pooling;
+mod recurrent;
+mod transformer;
+
+pub use cnn::CnnClassifier;
+pub use mean_pooling::MeanPoolingClassifier;
+pub use recurrent::RecurrentClassifier;
+pub use transformer::TransformerClassifier;
+
+use candle_core::{Result, Tensor, D};
+use candle_nn::{Dropout, Embedding, Linear, Module, VarBuilder};
+use std::fmt::Debug;
+
+use candle_core::Device;
+use serde::{Deserialize, Serialize};
+
+use super::PAD_INDEX;
+
+/// The neural network architecture of a classifier.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum Architecture {
+    /// Mean-pooled embeddings followed by two linear layers.
+    #[default]
+    MeanPooling,
+    /// Convolutions of several widths over the embeddings, max-pooled over time (Kim, 2014).
+    Cnn,
+    /// A bidirectional LSTM over the embeddings.
+    Lstm,
+    /// A bidirectional GRU over the embeddings.
+    Gru,
+    /// A Transformer encoder over the embeddings and learned positional embeddings.
+    Transformer,
+}
+
+/// How the outputs of a sequence encoder are reduced to a single vector per text.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum Pooling {
+    /// The final states of both directions, i.e. after the last token and before the first one.
+    Last,
+    /// The maximum of every dimension over the tokens.
+    Max,
+    /// The mean over the tokens.
+    #[default]
+    Mean,
+    /// The output of a learned token prepended to every text, for the `transformer` architecture.
+    Cls,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ModelConfig {
+    #[serde(skip, default = "default_device")]
+    pub device: Device,
+    pub architecture: Architecture,
+    pub vocab_size: usize,
+    pub embedding_size: usize,
+    pub hidden_size: usize,
+    pub n_classes: usize,
+    pub max_seq_len: usize,
+    /// Widths of the convolution kernels of the `cnn` architecture, with `n_filters` filters each.
+    pub kernel_sizes: Vec<usize>,
+    pub n_filters: usize,
+    /// Pooling of the outputs of the `lstm`, `gru` and `transformer` architectures. The state
+    /// size per direction of `lstm` and `gru` is `hidden_size`.
+    pub pooling: Pooling,
+    /// Number of layers of the `transformer` architecture, whose feed-forward size is
+    /// `hidden_size`.
+    pub n_layers: usize,
+    /// Number of attention heads of the `transformer` architecture. Must divide `embedding_size`.
+    pub n_heads: usize,
+    /// Probability of zeroing a feature before the classifier during training. Not used by the
+    /// `mean_pooling` architecture.
+    pub dropout: f32,
+}
+
+pub const MAX_SEQ_LEN: usize = 128;
+
+/// Name of the variable holding the embedding table of every architecture, of shape
+/// `(vocab_size, embedding_size)`.
+pub const EMBEDDING_VARIABLE: &str = "embedding.weight";
+
+fn default_device() -> Device {
+    Device::Cpu
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        Self {
+            device: default_device(),
+            architecture: Architecture::default(),
+            vocab_size: 1000,
+            embedding_size: 15,
+            hidden_size: 20,
+            n_classes: 2,
+            max_seq_len: MAX_SEQ_LEN,
+            kernel_sizes: vec![2, 3, 4],
+            n_filters: 16,
+            pooling: Pooling::default(),
+            n_layers: 2,
+            n_heads: 3,
+            dropout: 0.5,
+        }
+    }
+}
+
+/// A model turning the token indices of a batch of texts into one logit per class.
+///
+/// The training loop and the inference code only go through this trait, so an architecture is
+/// added by implementing it and building it in `build_classifier`.
+pub trait TextClassifier: Debug + Send + Sync {
+    /// Run the model on a batch of token indices of shape `(batch, seq_len)`, applying dropout
+    /// when `train` is set.
+    ///
+    /// `mask` has the same shape and holds 1 for the tokens and 0 for the padding, as returned by
+    /// `padding_mask`, so that the padding does not change the predictions. The returned logits
+    /// have shape `(batch, n_classes)`.
+    fn forward_t(&self, input_ids: &Tensor, mask: &Tensor, train: bool) -> Result<Tensor>;
+
+    /// Run the model like `forward_t`, in evaluation mode.
+    fn forward(&self, input_ids: &Tensor, mask: &Tensor) -> Result<Tensor> {
+        self.forward_t(input_ids, mask, false)
+    }
+
+    /// The configuration the model was built from, from which `build_classifier` builds it again.
+    fn config(&self) -> &ModelConfig;
+
+    /// The number of values of the variables of the model, including the embeddings frozen during
+    /// training.
+    fn n_parameters(&self) -> usize;
+}
+
+/// Build the classifier of the architecture set in the configuration, creating its variables
+/// with the VarBuilder.
+///
+/// # Arguments
+///
+/// * `vb` - A reference to a VarBuilder used for creating variables.
+/// * `config` - A reference to a ModelConfig, e.g. the one stored in the model bundle.
+///
+/// # Errors
+///
+/// This function returns an error if the configuration of the architecture is invalid, or if the
+/// layers cannot be created with the VarBuilder.
+pub fn build_classifier(vb: &VarBuilder, config: &ModelConfig) -> Result<Box<dyn TextClassifier>> {
+    let model: Box<dyn TextClassifier> = match config.architecture {
+        Architecture::MeanPooling => Box::new(MeanPoolingClassifier::new(vb, config)?),
+        Architecture::Cnn => Box::new(CnnClassifier::new(vb, config)?),
+        Architecture::Lstm | Architecture::Gru => Box::new(RecurrentClassifier::new(vb, config)?),
+        Architecture::Transformer => Box::new(TransformerClassifier::new(vb, config)?),
+    };
+    Ok(model)
+}
+
+/// The mask of a batch of token indices, holding 1 for the tokens and 0 for the `PAD_INDEX`
+/// positions, with the same shape as the indices.
+pub fn padding_mask(input_ids: &Tensor) -> Result<Tensor> {
+    input_ids.ne(PAD_INDEX)?.to_dtype(candle_core::DType::F32)
+}
+
+/// The dropout before the classifier, checking its probability.
+fn dropout(config: &ModelConfig) -> Result<Dropout> {
+    if !(0.0..1.0).contains(&config.dropout) {
+        candle_core::bail!("The dropout must be in [0, 1), got {}", config.dropout);
+    }
+    Ok(Dropout::new(config.dropout))
+}
+
+/// Check the shapes of the inputs and embed the token indices. Returns the embeddings of shape
+/// `(batch, seq_len, embedding_size)` and the mask of shape `(batch, seq_len, 1)`, in the dtype of
+/// the embeddings.
+fn embed(embedding: &Embedding, input_ids: &Tensor, mask: &Tensor) -> Result<(Tensor, Tensor)> {
+    if input_ids.rank() != 2 {
+        candle_core::bail!(
+            "Expected token indices of shape (batch, seq_len), got shape {:?}",
+            input_ids.shape()
+        );
+    }
+    if mask.dims() != input_ids.dims() {
+        candle_core::bail!(
+            "Expected a mask of the shape of the token indices {:?}, got shape {:?}",
+            input_ids.shape(),
+            mask.shape()
+        );
+    }
+
+    let embeddings = embedding.forward(input_ids)?;
+    log::debug!("Embeddings - Shape: {:?}", embeddings.shape());
+
+    let mask = mask.to_dtype(embeddings.dtype())?.unsqueeze(D::Minus1)?;
+    Ok((embeddings, mask))
+}
+
+/// The mean of `xs` of shape `(batch, seq_len, features)` over the tokens only, `mask` of shape
+/// `(batch, seq_len, 1)` marking them with 1, so that the padding does not dilute the
+/// representation of short texts.
+fn masked_mean(xs: &Tensor, mask: &Tensor) -> Result<Tensor> {
+    let n_tokens = mask.sum(1)?.clamp(1f32, f32::MAX)?;
+    xs.broadcast_mul(mask)?.sum(1)?.broadcast_div(&n_tokens)
+}
+
+/// Turn the features of every text into logits.
+fn classify(classifier: &Linear, features: &Tensor) -> Result<Tensor> {
+    log::debug!(
+        "Features - Shape: {:?}, Values {:?}",
+        features.shape(),
+        features.get(0)
+    );
+    classifier.forward(features)
+}
+
+/// The number of values of a layer with a weight and an optional bias.
+fn n_elements(weight: &Tensor, bias: Option<&Tensor>) -> usize {
+    weight.elem_count() + bias.map_or(0, Tensor::elem_count)
+}
@@ -0,0 +1,227 @@
+use candle_core::{Device, Result, Tensor, D};
+use candle_nn::{
+    embedding, gru, linear, lstm, Dropout, Embedding, GRUConfig, LSTMConfig, Linear, VarBuilder,
+    GRU, LSTM, RNN,
+};
+
+use super::{
+    classify, dropout, embed, masked_mean, n_elements, Architecture, ModelConfig, Pooling,
+    TextClassifier,
+};
+
+/// A recurrent layer per direction.
+#[derive(Debug)]
+enum BidirectionalRnn {
+    Lstm { forward: LSTM, backward: LSTM },
+    Gru { forward: GRU, backward: GRU },
+}
+
+impl BidirectionalRnn {
+    /// Run both directions over the sequences, the backward one over `reversed_inputs`. Returns
+    /// the outputs of every step, of shape `(batch, seq_len, hidden_size)`.
+    fn outputs(&self, inputs: &Tensor, reversed_inputs: &Tensor) -> Result<(Tensor, Tensor)> {
+        fn run<R: RNN>(rnn: &R, inputs: &Tensor) -> Result<Tensor> {
+            let (batch_size, seq_len, _) = inputs.dims3()?;
+            // The states are concatenated along the feature dimension
+            rnn.states_to_tensor(&rnn.seq(inputs)?)?
+                .reshape((batch_size, seq_len, ()))
+        }
+
+        match self {
+            Self::Lstm { forward, backward } => {
+                Ok((run(forward, inputs)?, run(backward, reversed_inputs)?))
+            }
+            Self::Gru { forward, backward } => {
+                Ok((run(forward, inputs)?, run(backward, reversed_inputs)?))
+            }
+        }
+    }
+
+    /// The number of values of both directions. The candle layers do not expose their weights,
+    /// so they are counted from the sizes: every gate has an input and a hidden weight, and a
+    /// bias for each.
+    fn n_parameters(&self, in_dim: usize, hidden_dim: usize) -> usize {
+        let n_gates = match self {
+            Self::Lstm { .. } => 4,
+            Self::Gru { .. } => 3,
+        };
+        2 * n_gates * hidden_dim * (in_dim + hidden_dim + 2)
+    }
+}
+
+/// Indices reversing the first `length` positions of every row and keeping the padding after
+/// them, so that a reversed sequence is still padded on the right. Applying them twice restores
+/// the original order.
+fn reversal_indices(lengths: &[usize], seq_len: usize, device: &Device) -> Result<Tensor> {
+    let indices: Vec<u32> = lengths
+        .iter()
+        .flat_map(|&length| {
+            (0..seq_len).map(move |position| {
+                if position < length {
+                    (length - 1 - position) as u32
+                } else {
+                    position as u32
+                }
+            })
+        })
+        .collect();
+    Tensor::from_vec(indices, (lengths.len(), seq_len), device)
+}
+
+/// Gather the positions `indices` of shape `(batch, n_positions)` along the time dimension of
+/// `xs`, of shape `(batch, seq_len, features)`. Returns a tensor of shape
+/// `(batch, n_positions, features)`.
+fn gather_positions(xs: &Tensor, indices: &Tensor) -> Result<Tensor> {
+    let (batch_size, n_positions) = indices.dims2()?;
+    let indices = indices
+        .unsqueeze(D::Minus1)?
+        .broadcast_as((batch_size, n_positions, xs.dim(D::Minus1)?))?
+        .contiguous()?;
+    xs.contiguous()?.gather(&indices, 1)
+}
+
+/// The `lstm` and `gru` architectures: a bidirectional RNN over the embeddings, whose outputs
+/// are pooled.
+#[derive(Debug)]
+pub struct RecurrentClassifier {
+    embedding: Embedding,
+    rnn: BidirectionalRnn,
+    pooling: Pooling,
+    dropout: Dropout,
+    classifier: Linear,
+    config: ModelConfig,
+}
+
+impl RecurrentClassifier {
+    /// Create a new instance of a model using the provided VarBuilder and ModelConfig.
+    ///
+    /// # Arguments
+    ///
+    /// * `vb` - A reference to a VarBuilder used for creating variables.
+    /// * `config` - A reference to a ModelConfig containing configuration parameters for the model.
+    ///
+    /// # Errors
+    ///
+    /// This function can return an error if the architecture is not `lstm` or `gru`, if the pooling or the dropout are invalid, or if there are issues with creating the layers using the VarBuilder.
+    pub fn new(vb: &VarBuilder, config: &ModelConfig) -> Result<Self> {
+        if config.pooling == Pooling::Cls {
+            candle_core::bail!("The lstm and gru architectures do not support the cls pooling");
+        }
+
+        let (in_dim, hidden_dim) = (config.embedding_size, config.hidden_size);
+        let rnn_vb = vb.pp("rnn");
+        let rnn = match config.architecture {
+            Architecture::Lstm => BidirectionalRnn::Lstm {
+                forward: lstm(
+                    in_dim,
+                    hidden_dim,
+                    LSTMConfig::default(),
+                    rnn_vb.pp("forward"),
+                )?,
+                backward: lstm(
+                    in_dim,
+                    hidden_dim,
+                    LSTMConfig::default(),
+                    rnn_vb.pp("backward"),
+                )?,
+            },
+            Architecture::Gru => BidirectionalRnn::Gru {
+                forward: gru(
+                    in_dim,
+                    hidden_dim,
+                    GRUConfig::default(),
+                    rnn_vb.pp("forward"),
+                )?,
+                backward: gru(
+                    in_dim,
+                    hidden_dim,
+                    GRUConfig::default(),
+                    rnn_vb.pp("backward"),
+                )?,
+            },
+            architecture => candle_core::bail!(
+                "The recurrent classifier does not support the {:?} architecture",
+                architecture
+            ),
+        };
+
+        Ok(Self {
+            embedding: embedding(config.vocab_size, config.embedding_size, vb.pp("embedding"))?,
+            rnn,
+            pooling: config.pooling,
+            dropout: dropout(config)?,
+            classifier: linear(2 * hidden_dim, config.n_classes, vb.pp("classifier"))?,
+            config: config.clone(),
+        })
+    }
+
+    /// Run the bidirectional RNN over the tokens of every text, and pool its outputs.
+    ///
+    /// The texts are padded on the right, so the forward direction reaches the padding only after
+    /// the last token. The backward direction runs over every text with its tokens reversed and
+    /// the padding kept at the end, so it starts on the last token rather than on the padding.
+    fn encode(&self, embeddings: &Tensor, mask: &Tensor) -> Result<Tensor> {
+        let lengths: Vec<usize> = mask
+            .sum(1)?
+            .squeeze(D::Minus1)?
+            .to_vec1::<f32>()?
+            .into_iter()
+            .map(|length| (length as usize).max(1))
+            .collect();
+        // Skip the steps that only read padding
+        let seq_len = lengths.iter().copied().max().unwrap_or(1);
+        let embeddings = embeddings.narrow(1, 0, seq_len)?;
+        let mask = mask.narrow(1, 0, seq_len)?;
+
+        let reversal = reversal_indices(&lengths, seq_len, embeddings.device())?;
+        let reversed_embeddings = gather_positions(&embeddings, &reversal)?;
+        let (forward, reversed_backward) = self.rnn.outputs(&embeddings, &reversed_embeddings)?;
+
+        match self.pooling {
+            Pooling::Last => {
+                let last = Tensor::from_vec(
+                    lengths.iter().map(|&length| length as u32 - 1).collect(),
+                    (lengths.len(), 1),
+                    embeddings.device(),
+                )?;
+                let forward_last = gather_positions(&forward, &last)?.squeeze(1)?;
+                let backward_last = gather_positions(&reversed_backward, &last)?.squeeze(1)?;
+                Tensor::cat(&[forward_last, backward_last], 1)
+            }
+            // The cls pooling is rejected when building the model
+            Pooling::Max | Pooling::Mean | Pooling::Cls => {
+                let backward = gather_positions(&reversed_backward, &reversal)?;
+                let outputs = Tensor::cat(&[forward, backward], D::Minus1)?;
+                if self.pooling == Pooling::Mean {
+                    masked_mean(&outputs, &mask)
+                } else {
+                    // Push the padding far below the outputs, which are within [-1, 1]
+                    let padding_offset = ((&mask - 1.0)? * 1e4)?;
+                    outputs.broadcast_add(&padding_offset)?.max(1)
+                }
+            }
+        }
+    }
+}
+
+impl TextClassifier for RecurrentClassifier {
+    fn forward_t(&self, input_ids: &Tensor, mask: &Tensor, train: bool) -> Result<Tensor> {
+        let (embeddings, mask) = embed(&self.embedding, input_ids, mask)?;
+        let features = self
+            .dropout
+            .forward(&self.encode(&embeddings, &mask)?, train)?;
+        classify(&self.classifier, &features)
+    }
+
+    fn config(&self) -> &ModelConfig {
+        &self.config
+    }
+
+    fn n_parameters(&self) -> usize {
+        self.embedding.embeddings().elem_count()
+            + self
+                .rnn
+                .n_parameters(self.config.embedding_size, self.config.hidden_size)
+            + n_elements(self.classifier.weight(), self.classifier.bias())
+    }
+}
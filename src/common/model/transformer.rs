@@ -0,0 +1,257 @@
+use candle_core::{Result, Tensor, D};
+use candle_nn::ops::softmax;
+use candle_nn::{
+    embedding, layer_norm, linear, Dropout, Embedding, LayerNorm, Linear, Module, VarBuilder,
+};
+
+use super::{
+    classify, dropout, embed, masked_mean, n_elements, ModelConfig, Pooling, TextClassifier,
+};
+
+/// Multi-head self-attention, ignoring the padding positions.
+#[derive(Debug)]
+struct SelfAttention {
+    query: Linear,
+    key: Linear,
+    value: Linear,
+    output: Linear,
+    n_heads: usize,
+}
+
+impl SelfAttention {
+    fn new(size: usize, n_heads: usize, vb: VarBuilder) -> Result<Self> {
+        Ok(Self {
+            query: linear(size, size, vb.pp("query"))?,
+            key: linear(size, size, vb.pp("key"))?,
+            value: linear(size, size, vb.pp("value"))?,
+            output: linear(size, size, vb.pp("output"))?,
+            n_heads,
+        })
+    }
+
+    /// Attend over `xs` of shape `(batch, seq_len, size)`. `key_mask` of shape
+    /// `(batch, 1, 1, seq_len)` holds 0 for the tokens and a large negative value for the padding.
+    fn forward(&self, xs: &Tensor, key_mask: &Tensor) -> Result<Tensor> {
+        let (batch_size, seq_len, size) = xs.dims3()?;
+        let head_size = size / self.n_heads;
+
+        // (batch, n_heads, seq_len, head_size)
+        let heads = |projection: &Linear| -> Result<Tensor> {
+            projection
+                .forward(xs)?
+                .reshape((batch_size, seq_len, self.n_heads, head_size))?
+                .transpose(1, 2)?
+                .contiguous()
+        };
+        let (query, key, value) = (heads(&self.query)?, heads(&self.key)?, heads(&self.value)?);
+
+        let scores = (query.matmul(&key.t()?)? / (head_size as f64).sqrt())?;
+        let weights = softmax(&scores.broadcast_add(key_mask)?, D::Minus1)?;
+        let attended = weights
+            .matmul(&value)?
+            .transpose(1, 2)?
+            .reshape((batch_size, seq_len, size))?;
+        self.output.forward(&attended)
+    }
+
+    fn n_parameters(&self) -> usize {
+        [&self.query, &self.key, &self.value, &self.output]
+            .iter()
+            .map(|projection| n_elements(projection.weight(), projection.bias()))
+            .sum()
+    }
+}
+
+/// A pre-norm Transformer encoder layer.
+#[derive(Debug)]
+struct TransformerLayer {
+    attention_norm: LayerNorm,
+    attention: SelfAttention,
+    feed_forward_norm: LayerNorm,
+    feed_forward_in: Linear,
+    feed_forward_out: Linear,
+}
+
+impl TransformerLayer {
+    fn new(config: &ModelConfig, vb: VarBuilder) -> Result<Self> {
+        let size = config.embedding_size;
+        Ok(Self {
+            attention_norm: layer_norm(size, 1e-5, vb.pp("attention_norm"))?,
+            attention: SelfAttention::new(size, config.n_heads, vb.pp("attention"))?,
+            feed_forward_norm: layer_norm(size, 1e-5, vb.pp("feed_forward_norm"))?,
+            feed_forward_in: linear(size, config.hidden_size, vb.pp("feed_forward_in"))?,
+            feed_forward_out: linear(config.hidden_size, size, vb.pp("feed_forward_out"))?,
+        })
+    }
+
+    fn forward(&self, xs: &Tensor, key_mask: &Tensor) -> Result<Tensor> {
+        let attended = self
+            .attention
+            .forward(&self.attention_norm.forward(xs)?, key_mask)?;
+        let xs = (xs + attended)?;
+
+        let transformed = self.feed_forward_out.forward(
+            &self
+                .feed_forward_in
+                .forward(&self.feed_forward_norm.forward(&xs)?)?
+                .gelu()?,
+        )?;
+        xs + transformed
+    }
+
+    fn n_parameters(&self) -> usize {
+        n_elements(self.attention_norm.weight(), self.attention_norm.bias())
+            + self.attention.n_parameters()
+            + n_elements(
+                self.feed_forward_norm.weight(),
+                self.feed_forward_norm.bias(),
+            )
+            + n_elements(self.feed_forward_in.weight(), self.feed_forward_in.bias())
+            + n_elements(self.feed_forward_out.weight(), self.feed_forward_out.bias())
+    }
+}
+
+/// The Transformer encoder, with its positional embeddings and optional CLS token.
+#[derive(Debug)]
+struct Transformer {
+    positions: Embedding,
+    /// The embedding of the token prepended to every text, for the CLS pooling.
+    cls: Option<Tensor>,
+    layers: Vec<TransformerLayer>,
+    norm: LayerNorm,
+}
+
+impl Transformer {
+    fn new(config: &ModelConfig, vb: VarBuilder) -> Result<Self> {
+        let size = config.embedding_size;
+        if config.n_heads == 0 || !size.is_multiple_of(config.n_heads) {
+            candle_core::bail!(
+                "The transformer needs a number of heads dividing the embedding size {}, got {}",
+                size,
+                config.n_heads
+            );
+        }
+        if config.pooling == Pooling::Last {
+            candle_core::bail!("The transformer does not support the last pooling");
+        }
+
+        let cls = if config.pooling == Pooling::Cls {
+            Some(vb.get_with_hints((1, 1, size), "cls", candle_nn::init::DEFAULT_KAIMING_NORMAL)?)
+        } else {
+            None
+        };
+        // One more position for the CLS token
+        let n_positions = config.max_seq_len + usize::from(cls.is_some());
+        let layers = (0..config.n_layers)
+            .map(|index| TransformerLayer::new(config, vb.pp("layers").pp(index)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            positions: embedding(n_positions, size, vb.pp("positions"))?,
+            cls,
+            layers,
+            norm: layer_norm(size, 1e-5, vb.pp("norm"))?,
+        })
+    }
+
+    /// Encode the embeddings of shape `(batch, seq_len, size)` and pool the outputs of the
+    /// tokens, `mask` of shape `(batch, seq_len, 1)` marking the tokens with 1.
+    fn forward(&self, embeddings: &Tensor, mask: &Tensor, pooling: Pooling) -> Result<Tensor> {
+        // The padding is on the right, so the positions after the longest text can be skipped
+        let seq_len = (mask.sum(1)?.max(0)?.squeeze(0)?.to_scalar::<f32>()? as usize).max(1);
+        let mut xs = embeddings.narrow(1, 0, seq_len)?;
+        let mut mask = mask.narrow(1, 0, seq_len)?;
+        let batch_size = xs.dim(0)?;
+
+        if let Some(cls) = &self.cls {
+            let cls = cls.broadcast_as((batch_size, 1, xs.dim(2)?))?;
+            xs = Tensor::cat(&[&cls, &xs], 1)?;
+            mask = Tensor::cat(&[&mask.narrow(1, 0, 1)?.ones_like()?, &mask], 1)?;
+        }
+
+        let n_positions = xs.dim(1)?;
+        let positions = Tensor::arange(0u32, n_positions as u32, xs.device())?;
+        xs = xs.broadcast_add(&self.positions.forward(&positions)?)?;
+
+        // (batch, 1, 1, seq_len), broadcast over the heads and the queries
+        let key_mask = ((mask.squeeze(D::Minus1)? - 1.0)? * 1e9)?
+            .unsqueeze(1)?
+            .unsqueeze(1)?;
+        for layer in &self.layers {
+            xs = layer.forward(&xs, &key_mask)?;
+        }
+        let xs = self.norm.forward(&xs)?;
+
+        match pooling {
+            Pooling::Cls => xs.narrow(1, 0, 1)?.squeeze(1),
+            Pooling::Max => xs.broadcast_add(&((&mask - 1.0)? * 1e4)?)?.max(1),
+            // The last pooling is rejected when building the model
+            Pooling::Mean | Pooling::Last => masked_mean(&xs, &mask),
+        }
+    }
+
+    fn n_parameters(&self) -> usize {
+        self.positions.embeddings().elem_count()
+            + self.cls.as_ref().map_or(0, Tensor::elem_count)
+            + self
+                .layers
+                .iter()
+                .map(TransformerLayer::n_parameters)
+                .sum::<usize>()
+            + n_elements(self.norm.weight(), self.norm.bias())
+    }
+}
+
+/// The `transformer` architecture: a Transformer encoder over the embeddings and learned
+/// positional embeddings, whose outputs are pooled.
+#[derive(Debug)]
+pub struct TransformerClassifier {
+    embedding: Embedding,
+    transformer: Transformer,
+    pooling: Pooling,
+    dropout: Dropout,
+    classifier: Linear,
+    config: ModelConfig,
+}
+
+impl TransformerClassifier {
+    /// Create a new instance of a model using the provided VarBuilder and ModelConfig.
+    ///
+    /// # Arguments
+    ///
+    /// * `vb` - A reference to a VarBuilder used for creating variables.
+    /// * `config` - A reference to a ModelConfig containing configuration parameters for the model.
+    ///
+    /// # Errors
+    ///
+    /// This function can return an error if the number of heads, the pooling or the dropout are invalid, or if there are issues with creating the layers using the VarBuilder.
+    pub fn new(vb: &VarBuilder, config: &ModelConfig) -> Result<Self> {
+        Ok(Self {
+            embedding: embedding(config.vocab_size, config.embedding_size, vb.pp("embedding"))?,
+            transformer: Transformer::new(config, vb.pp("transformer"))?,
+            pooling: config.pooling,
+            dropout: dropout(config)?,
+            classifier: linear(config.embedding_size, config.n_classes, vb.pp("classifier"))?,
+            config: config.clone(),
+        })
+    }
+}
+
+impl TextClassifier for TransformerClassifier {
+    fn forward_t(&self, input_ids: &Tensor, mask: &Tensor, train: bool) -> Result<Tensor> {
+        let (embeddings, mask) = embed(&self.embedding, input_ids, mask)?;
+        let encoded = self.transformer.forward(&embeddings, &mask, self.pooling)?;
+        let features = self.dropout.forward(&encoded, train)?;
+        classify(&self.classifier, &features)
+    }
+
+    fn config(&self) -> &ModelConfig {
+        &self.config
+    }
+
+    fn n_parameters(&self) -> usize {
+        self.embedding.embeddings().elem_count()
+            + self.transformer.n_parameters()
+            + n_elements(self.classifier.weight(), self.classifier.bias())
+    }
+}
@@ -1,3 +1,48 @@
+use std::path::Path;
+
 pub const MODEL_PATH: &str = "model/model.bin";
-pub const INDEX_TO_CLASS_PATH: &str = "data/index_to_class.json";
-pub const VOCAB_PATH: &str = "data/vocab.json";
+pub const MODEL_CONFIG_PATH: &str = "model/model_config.json";
+pub const INDEX_TO_CLASS_PATH: &str = "model/index_to_class.json";
+pub const VOCAB_PATH: &str = "model/vocab.json";
+
+/// Locations of the files produced by a training run.
+#[derive(Debug, Clone)]
+pub struct ArtifactPaths {
+    pub model: String,
+    pub model_config: String,
+    pub vocabulary: String,
+    pub index_to_class: String,
+}
+
+impl Default for ArtifactPaths {
+    fn default() -> Self {
+        Self {
+            model: MODEL_PATH.to_string(),
+            model_config: MODEL_CONFIG_PATH.to_string(),
+            vocabulary: VOCAB_PATH.to_string(),
+            index_to_class: INDEX_TO_CLASS_PATH.to_string(),
+        }
+    }
+}
+
+impl ArtifactPaths {
+    /// The artifacts inside `directory`, using the file names of the default locations.
+    pub fn in_directory(directory: &str) -> Self {
+        let join = |default_path: &str| {
+            let file_name = Path::new(default_path)
+                .file_name()
+                .expect("default artifact paths point to files");
+            Path::new(directory)
+                .join(file_name)
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        Self {
+            model: join(MODEL_PATH),
+            model_config: join(MODEL_CONFIG_PATH),
+            vocabulary: join(VOCAB_PATH),
+            index_to_class: join(INDEX_TO_CLASS_PATH),
+        }
+    }
+}
@@ -19,7 +19,7 @@ pub fn pad_vector<T: Clone>(mut vector: Vec<T>, max_padding: usize, pad_value: T
     match vector.len() {
         len if len > max_padding => vector.truncate(max_padding),
         len if len < max_padding => {
-            vector.extend(std::iter::repeat(pad_value).take(max_padding - len))
+            vector.extend(std::iter::repeat_n(pad_value, max_padding - len))
         }
         _ => (),
     }
@@ -0,0 +1,83 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+/// Settings controlling how raw text is split into tokens.
+///
+/// The configuration is persisted together with the vocabulary, so that the inference server
+/// tokenizes requests exactly the way the training data was tokenized.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct TokenizerConfig {
+    pub lowercase: bool,
+    pub strip_punctuation: bool,
+    pub normalize_unicode: bool,
+}
+
+impl Default for TokenizerConfig {
+    fn default() -> Self {
+        Self {
+            lowercase: true,
+            strip_punctuation: true,
+            normalize_unicode: true,
+        }
+    }
+}
+
+/// Splits a piece of text into the tokens that are looked up in the vocabulary.
+pub trait Tokenizer {
+    /// Split `text` into a list of tokens.
+    fn tokenize(&self, text: &str) -> Vec<String>;
+
+    /// The configuration that has to be persisted to rebuild an identical tokenizer.
+    fn config(&self) -> &TokenizerConfig;
+}
+
+/// Tokenizer applying Unicode normalization, lowercasing and punctuation stripping, in this
+/// order, before splitting on whitespace. Each step can be turned off through `TokenizerConfig`.
+#[derive(Debug, Clone)]
+pub struct DefaultTokenizer {
+    config: TokenizerConfig,
+    punctuation_regex: Regex,
+}
+
+impl DefaultTokenizer {
+    pub fn new(config: TokenizerConfig) -> Self {
+        Self {
+            config,
+            punctuation_regex: Regex::new(r"[[:punct:]\p{P}]").unwrap(),
+        }
+    }
+}
+
+impl Default for DefaultTokenizer {
+    fn default() -> Self {
+        Self::new(TokenizerConfig::default())
+    }
+}
+
+impl Tokenizer for DefaultTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        let mut text = text.to_string();
+
+        // NFKC folds compatibility characters (e.g. full-width letters, ligatures) into their
+        // canonical form, so visually identical words map to the same vocabulary entry.
+        if self.config.normalize_unicode {
+            text = text.nfkc().collect();
+        }
+
+        if self.config.lowercase {
+            text = text.to_lowercase();
+        }
+
+        if self.config.strip_punctuation {
+            text = self.punctuation_regex.replace_all(&text, "").into_owned();
+        }
+
+        text.split_whitespace().map(|s| s.to_string()).collect()
+    }
+
+    fn config(&self) -> &TokenizerConfig {
+        &self.config
+    }
+}
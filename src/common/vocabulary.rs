@@ -1,45 +1,196 @@
+use super::encode::{PAD_TOKEN, UNK_TOKEN};
 use super::exception::VocabularyLoadError;
-use regex::Regex;
+use super::tokenize::{Tokenizer, TokenizerConfig};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashMap, HashSet},
     fs::File,
     io::{Read, Write},
 };
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Vocabulary {
-    vocabulary: Vec<String>,
+/// Reserved tokens placed at the start of the index space, before the corpus words.
+///
+/// `<PAD>` and `<UNK>` always take the indices `PAD_INDEX` and `UNK_INDEX`. The optional
+/// `<BOS>`/`<EOS>` markers, when enabled, take the next indices in this order.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SpecialTokens {
+    pub pad: String,
+    pub unk: String,
+    pub bos: Option<String>,
+    pub eos: Option<String>,
+}
+
+impl Default for SpecialTokens {
+    fn default() -> Self {
+        Self {
+            pad: PAD_TOKEN.to_string(),
+            unk: UNK_TOKEN.to_string(),
+            bos: None,
+            eos: None,
+        }
+    }
+}
+
+impl SpecialTokens {
+    /// The reserved tokens in the order of their indices.
+    pub fn tokens(&self) -> Vec<&str> {
+        let mut tokens = vec![self.pad.as_str(), self.unk.as_str()];
+        tokens.extend(self.bos.as_deref());
+        tokens.extend(self.eos.as_deref());
+        tokens
+    }
+}
+
+/// A vocabulary together with the configuration of the tokenizer that produced it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Vocabulary {
+    pub vocabulary: Vec<String>,
+    #[serde(default)]
+    pub tokenizer: TokenizerConfig,
+    #[serde(default)]
+    pub special_tokens: SpecialTokens,
+    /// Number of occurrences of each word in the corpus the vocabulary was built from.
+    #[serde(default)]
+    pub counts: BTreeMap<String, usize>,
+}
+
+impl Vocabulary {
+    /// The number of indices used by the vocabulary, including the special tokens.
+    ///
+    /// This is the minimum size of the embedding table of a model using this vocabulary.
+    pub fn n_indices(&self) -> usize {
+        self.vocabulary.len() + self.special_tokens.tokens().len()
+    }
+}
+
+/// Builds a `Vocabulary` from a corpus by counting token frequencies.
+///
+/// Tokens are sorted by decreasing frequency, ties being broken lexicographically, so the same
+/// corpus always produces the same vocabulary and the same word indices. Tokens occurring less than
+/// `min_freq` times and stop words are dropped, and only the `max_size` most frequent tokens are kept.
+///
+/// Stop words are compared against the tokenized words, so they should be given in the form the
+/// tokenizer produces (e.g. lowercased).
+#[derive(Debug, Clone)]
+pub struct VocabularyBuilder {
+    min_freq: usize,
+    max_size: Option<usize>,
+    stop_words: HashSet<String>,
+    special_tokens: SpecialTokens,
+}
+
+impl Default for VocabularyBuilder {
+    fn default() -> Self {
+        Self {
+            min_freq: 1,
+            max_size: None,
+            stop_words: HashSet::new(),
+            special_tokens: SpecialTokens::default(),
+        }
+    }
+}
+
+impl VocabularyBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop the tokens occurring less than `min_freq` times in the corpus.
+    pub fn min_freq(mut self, min_freq: usize) -> Self {
+        self.min_freq = min_freq;
+        self
+    }
+
+    /// Keep at most `max_size` tokens, not counting the special tokens.
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Exclude the given words from the vocabulary. They are mapped to `<UNK>` when encoding.
+    pub fn stop_words<I, S>(mut self, stop_words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.stop_words
+            .extend(stop_words.into_iter().map(Into::into));
+        self
+    }
+
+    /// The reserved tokens stored with the vocabulary.
+    pub fn special_tokens(mut self, special_tokens: SpecialTokens) -> Self {
+        self.special_tokens = special_tokens;
+        self
+    }
+
+    /// Count the tokens of the corpus and build the vocabulary.
+    ///
+    /// # Arguments
+    ///
+    /// * `corpus` - A slice of strings containing sentences.
+    /// * `tokenizer` - The tokenizer used to split the sentences into tokens.
+    ///
+    /// # Returns
+    ///
+    /// The `Vocabulary`, along with the tokenizer configuration, the special tokens and the token counts.
+    pub fn build(&self, corpus: &[String], tokenizer: &dyn Tokenizer) -> Vocabulary {
+        let reserved = self.special_tokens.tokens();
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for sentence in corpus {
+            for word in tokenizer.tokenize(sentence) {
+                *counts.entry(word).or_insert(0) += 1;
+            }
+        }
+
+        let mut words: Vec<(String, usize)> = counts
+            .into_iter()
+            .filter(|(word, count)| {
+                *count >= self.min_freq
+                    && !self.stop_words.contains(word)
+                    && !reserved.contains(&word.as_str())
+            })
+            .collect();
+
+        words.sort_by(|(word_a, count_a), (word_b, count_b)| {
+            count_b.cmp(count_a).then_with(|| word_a.cmp(word_b))
+        });
+
+        if let Some(max_size) = self.max_size {
+            words.truncate(max_size);
+        }
+
+        Vocabulary {
+            vocabulary: words.iter().map(|(word, _)| word.clone()).collect(),
+            tokenizer: tokenizer.config().clone(),
+            special_tokens: self.special_tokens.clone(),
+            counts: words.into_iter().collect(),
+        }
+    }
 }
 
 /// Creates a vocabulary from a given corpus of sentences.
 ///
 /// The function takes a reference to a vector of strings representing a corpus
-/// of sentences and returns a vector of unique words found in the corpus. Words
-/// are separated by whitespace in each sentence.
+/// of sentences and returns a vector of unique tokens found in the corpus, ordered by
+/// decreasing frequency and then lexicographically. Sentences are split into tokens by
+/// the provided tokenizer, which must be the same one used to encode the data for
+/// training and inference. Use `VocabularyBuilder` for pruning options.
 ///
 /// # Arguments
 ///
 /// * `corpus` - A reference to a vector of strings containing sentences.
+/// * `tokenizer` - The tokenizer used to split the sentences into tokens.
 ///
 /// # Returns
 ///
-/// A vector of unique words found in the corpus.
-pub fn make_vocabulary(corpus: &Vec<String>) -> Vec<String> {
-    let mut vocabulary: HashSet<String> = HashSet::new();
-
-    let punctuation_regex = Regex::new(r"[[:punct:]]").unwrap();
-
-    for sentence in corpus {
-        let sentence_without_punctuation = punctuation_regex.replace_all(sentence, "");
-
-        let words: Vec<&str> = sentence_without_punctuation.split_whitespace().collect();
-
-        for word in words {
-            vocabulary.insert(word.to_string());
-        }
-    }
-    vocabulary.into_iter().collect::<Vec<String>>()
+/// A vector of unique tokens found in the corpus.
+pub fn make_vocabulary(corpus: &[String], tokenizer: &dyn Tokenizer) -> Vec<String> {
+    VocabularyBuilder::default()
+        .build(corpus, tokenizer)
+        .vocabulary
 }
 
 /// Load a vocabulary from a JSON file.
@@ -54,8 +205,8 @@ pub fn make_vocabulary(corpus: &Vec<String>) -> Vec<String> {
 ///
 /// # Returns
 ///
-/// This function returns a `Result<Vec<String>, VocabularyLoadError>`, where `Vec<String>` represents the loaded vocabulary on success, and `VocabularyLoadError` represents any encountered errors specific to vocabulary loading
-pub fn load_vocabulary(file_path: &str) -> Result<Vec<String>, VocabularyLoadError> {
+/// This function returns a `Result<Vocabulary, VocabularyLoadError>`, where `Vocabulary` represents the loaded vocabulary and its tokenizer configuration on success, and `VocabularyLoadError` represents any encountered errors specific to vocabulary loading
+pub fn load_vocabulary(file_path: &str) -> Result<Vocabulary, VocabularyLoadError> {
     let mut file = File::open(file_path)?;
     let mut json_data = String::new();
 
@@ -63,14 +214,14 @@ pub fn load_vocabulary(file_path: &str) -> Result<Vec<String>, VocabularyLoadErr
 
     let vocabulary: Vocabulary = serde_json::from_str(&json_data)?;
 
-    Ok(vocabulary.vocabulary)
+    Ok(vocabulary)
 }
 
 /// Store a vocabulary in a JSON file.
 ///
 /// # Arguments
 ///
-/// * `vocabulary` - A reference to a Vec<String> containing the vocabulary to be stored.
+/// * `vocabulary` - A reference to the `Vocabulary` to be stored, including its tokenizer configuration and special tokens.
 /// * `file_path` - A string containing the path to the JSON file where the vocabulary will be stored.
 ///
 /// # Errors
@@ -80,13 +231,8 @@ pub fn load_vocabulary(file_path: &str) -> Result<Vec<String>, VocabularyLoadErr
 /// # Returns
 ///
 /// This function returns `Result<(), anyhow::Error>`, where `()` indicates success, and `anyhow::Error` represents any encountered errors.
-pub fn store_vocabulary(vocabulary: &Vec<String>, file_path: &str) -> Result<(), anyhow::Error> {
+pub fn store_vocabulary(vocabulary: &Vocabulary, file_path: &str) -> Result<(), anyhow::Error> {
     let mut file = File::create(file_path)?;
-    file.write_all(
-        serde_json::to_string(&Vocabulary {
-            vocabulary: vocabulary.to_owned(),
-        })?
-        .as_bytes(),
-    )?;
+    file.write_all(serde_json::to_string(vocabulary)?.as_bytes())?;
     Ok(())
 }
@@ -1,5 +1,25 @@
 mod common;
+mod prediction;
+mod serving;
 mod training;
 
 pub use common::*;
+pub use prediction::classify;
+pub use prediction::config as prediction_config;
+pub use prediction::input;
+pub use prediction::output;
+pub use serving::batcher;
+pub use serving::config as serving_config;
+pub use serving::error;
+pub use serving::inference;
+pub use serving::loader;
+pub use serving::monitoring;
+pub use serving::pool;
+pub use serving::registry;
+pub use serving::routes;
+pub use serving::state;
+pub use serving::types;
+pub use training::config as training_config;
+pub use training::dataset;
+pub use training::embeddings;
 pub use training::metrics;
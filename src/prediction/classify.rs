@@ -0,0 +1,72 @@
+use super::input::Record;
+use super::output::Prediction;
+use crate::inference::{get_batch_predictions, score_labels, validate_texts};
+use crate::loader::SharedData;
+use crate::monitoring::ServingMetrics;
+use crate::types::PredictionOptions;
+use anyhow::Result;
+
+/// Classify a batch of records in a single forward pass.
+///
+/// The records that cannot be classified, e.g. empty texts, are logged and get no prediction,
+/// instead of failing the whole file.
+pub fn predict_batch(
+    records: Vec<Record>,
+    data: &SharedData,
+    options: &PredictionOptions,
+    metrics: &ServingMetrics,
+) -> Result<Vec<Prediction>> {
+    let is_valid: Vec<bool> = records
+        .iter()
+        .map(|record| validate_texts(std::slice::from_ref(&record.text), &data.tokenizer).is_ok())
+        .collect();
+
+    let texts: Vec<String> = records
+        .iter()
+        .zip(&is_valid)
+        .filter(|(_, &is_valid)| is_valid)
+        .map(|(record, _)| record.text.clone())
+        .collect();
+    let mut scores = get_batch_predictions(
+        &texts,
+        &data.tokenizer,
+        &data.word_to_index,
+        &data.special_tokens,
+        data.model.as_ref(),
+        data.max_seq_len,
+        metrics,
+    )?
+    .into_iter();
+
+    // Keep the predictions in the order of the records
+    let predictions = records
+        .into_iter()
+        .zip(is_valid)
+        .map(|(record, is_valid)| {
+            if !is_valid {
+                log::warn!(
+                    "Skipping the text {:?}, it does not contain any token",
+                    record.text
+                );
+                return Prediction {
+                    id: record.id,
+                    labels: Vec::new(),
+                    scores: None,
+                };
+            }
+
+            let scores = scores.next().expect("one prediction per valid text");
+            Prediction {
+                id: record.id,
+                labels: score_labels(&scores, &data.index_to_class, &data.thresholds, options)
+                    .into_iter()
+                    .filter(|label| label.above_threshold)
+                    .map(|label| label.label)
+                    .collect(),
+                scores: Some(scores),
+            }
+        })
+        .collect();
+
+    Ok(predictions)
+}
@@ -0,0 +1,120 @@
+use crate::ArtifactPaths;
+use anyhow::{bail, Result};
+use clap::{Parser, ValueEnum};
+use std::path::Path;
+
+/// Format of the file of texts to classify.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Csv,
+    Jsonl,
+}
+
+/// Format of the file the predictions are written to.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Csv,
+    Jsonl,
+    Parquet,
+}
+
+/// The extension of `path`, in lower case.
+fn extension(path: &str) -> Option<String> {
+    Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+}
+
+/// Classify the texts of a CSV or JSONL file and write their predictions to a file.
+#[derive(Parser, Debug)]
+#[command(version)]
+pub struct Args {
+    /// File of texts to classify.
+    #[arg(long)]
+    pub input: String,
+
+    /// File the predictions are written to.
+    #[arg(long)]
+    pub output: String,
+
+    /// Format of the input file. Guessed from its extension when not set.
+    #[arg(long, value_enum)]
+    pub input_format: Option<InputFormat>,
+
+    /// Format of the output file. Guessed from its extension when not set.
+    #[arg(long, value_enum)]
+    pub output_format: Option<OutputFormat>,
+
+    /// Column, or JSON field, holding the texts.
+    #[arg(long, default_value = "text")]
+    pub text_column: String,
+
+    /// Column, or JSON field, holding the ids copied to the predictions. No id is written when not set.
+    #[arg(long)]
+    pub id_column: Option<String>,
+
+    /// Directory holding the model weights, model config, vocabulary and label mapping. When not
+    /// set, the artifacts are loaded from `model/`.
+    #[arg(long)]
+    pub model_dir: Option<String>,
+
+    /// Number of texts classified in a single forward pass.
+    #[arg(long, default_value_t = 256)]
+    pub batch_size: usize,
+
+    /// Minimum score of the predicted classes, replacing the per-class thresholds of the model.
+    #[arg(long)]
+    pub threshold: Option<f32>,
+
+    /// Log filter, e.g. `info` or `predict=debug`. Falls back to `RUST_LOG` when not set.
+    #[arg(long)]
+    pub log_level: Option<String>,
+}
+
+impl Args {
+    pub fn artifact_paths(&self) -> ArtifactPaths {
+        match &self.model_dir {
+            Some(directory) => ArtifactPaths::in_directory(directory),
+            None => ArtifactPaths::default(),
+        }
+    }
+
+    /// The input format, given or guessed from the extension of the input file.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if no format is given and the extension is not known.
+    pub fn input_format(&self) -> Result<InputFormat> {
+        if let Some(format) = self.input_format {
+            return Ok(format);
+        }
+        match extension(&self.input).as_deref() {
+            Some("csv") => Ok(InputFormat::Csv),
+            Some("jsonl") | Some("ndjson") => Ok(InputFormat::Jsonl),
+            _ => bail!(
+                "Cannot guess the format of {}, set it with --input-format",
+                self.input
+            ),
+        }
+    }
+
+    /// The output format, given or guessed from the extension of the output file.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if no format is given and the extension is not known.
+    pub fn output_format(&self) -> Result<OutputFormat> {
+        if let Some(format) = self.output_format {
+            return Ok(format);
+        }
+        match extension(&self.output).as_deref() {
+            Some("csv") => Ok(OutputFormat::Csv),
+            Some("jsonl") | Some("ndjson") => Ok(OutputFormat::Jsonl),
+            Some("parquet") => Ok(OutputFormat::Parquet),
+            _ => bail!(
+                "Cannot guess the format of {}, set it with --output-format",
+                self.output
+            ),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use super::config::InputFormat;
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines};
+
+/// A text to classify, and its id when an id column is set.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub id: Option<String>,
+    pub text: String,
+}
+
+/// Reads the records of a CSV or JSONL file, a batch at a time, so that files larger than the
+/// memory can be classified.
+pub enum RecordReader {
+    Csv {
+        records: csv::StringRecordsIntoIter<File>,
+        text_index: usize,
+        id_index: Option<usize>,
+    },
+    Jsonl {
+        lines: Lines<BufReader<File>>,
+        line_number: usize,
+        text_column: String,
+        id_column: Option<String>,
+    },
+}
+
+impl RecordReader {
+    /// Open the file at `path`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file of texts.
+    /// * `format` - The format of the file.
+    /// * `text_column` - The column, or JSON field, holding the texts.
+    /// * `id_column` - The column, or JSON field, holding the ids, if any.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the file cannot be opened, or if a CSV file does not have
+    /// the given columns.
+    pub fn open(
+        path: &str,
+        format: InputFormat,
+        text_column: &str,
+        id_column: Option<&str>,
+    ) -> Result<Self> {
+        match format {
+            InputFormat::Csv => {
+                let mut reader = csv::Reader::from_path(path)
+                    .with_context(|| format!("Failed to open {}", path))?;
+                let headers = reader.headers()?.clone();
+                let column_index = |column: &str| {
+                    headers
+                        .iter()
+                        .position(|header| header == column)
+                        .ok_or_else(|| anyhow!("{} does not have a {} column", path, column))
+                };
+
+                Ok(Self::Csv {
+                    text_index: column_index(text_column)?,
+                    id_index: id_column.map(column_index).transpose()?,
+                    records: reader.into_records(),
+                })
+            }
+            InputFormat::Jsonl => {
+                let file = File::open(path).with_context(|| format!("Failed to open {}", path))?;
+
+                Ok(Self::Jsonl {
+                    lines: BufReader::new(file).lines(),
+                    line_number: 0,
+                    text_column: text_column.to_string(),
+                    id_column: id_column.map(str::to_string),
+                })
+            }
+        }
+    }
+
+    /// Read up to `batch_size` records. An empty batch means the end of the file was reached.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if a record cannot be read, or if it does not have a text.
+    pub fn next_batch(&mut self, batch_size: usize) -> Result<Vec<Record>> {
+        let mut batch = Vec::with_capacity(batch_size);
+        while batch.len() < batch_size {
+            match self.next_record()? {
+                Some(record) => batch.push(record),
+                None => break,
+            }
+        }
+        Ok(batch)
+    }
+
+    fn next_record(&mut self) -> Result<Option<Record>> {
+        match self {
+            Self::Csv {
+                records,
+                text_index,
+                id_index,
+            } => {
+                let Some(record) = records.next().transpose()? else {
+                    return Ok(None);
+                };
+                let field = |index: usize| record.get(index).unwrap_or_default().to_string();
+
+                Ok(Some(Record {
+                    id: id_index.map(field),
+                    text: field(*text_index),
+                }))
+            }
+            Self::Jsonl {
+                lines,
+                line_number,
+                text_column,
+                id_column,
+            } => loop {
+                let Some(line) = lines.next().transpose()? else {
+                    return Ok(None);
+                };
+                *line_number += 1;
+                // Tolerate blank lines, e.g. a trailing new line
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let value: Value = serde_json::from_str(&line)
+                    .with_context(|| format!("Line {} is not valid JSON", line_number))?;
+                let text = value
+                    .get(text_column.as_str())
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "Line {} does not have a {} string",
+                            line_number,
+                            text_column
+                        )
+                    })?
+                    .to_string();
+                let id = id_column
+                    .as_deref()
+                    .and_then(|column| value.get(column))
+                    .and_then(|id| match id {
+                        Value::Null => None,
+                        Value::String(id) => Some(id.clone()),
+                        id => Some(id.to_string()),
+                    });
+
+                return Ok(Some(Record { id, text }));
+            },
+        }
+    }
+}
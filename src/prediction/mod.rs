@@ -0,0 +1,4 @@
+pub mod classify;
+pub mod config;
+pub mod input;
+pub mod output;
@@ -0,0 +1,191 @@
+use super::config::OutputFormat;
+use anyhow::{Context, Result};
+use polars::io::parquet::BatchedWriter;
+use polars::prelude::{DataFrame, DataType, Field, NamedFrom, ParquetWriter, Schema, Series};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Separates the labels of a text in the CSV and Parquet files, as in the training data.
+const LABEL_SEPARATOR: &str = "|";
+
+/// The predictions of a text.
+#[derive(Debug, Clone)]
+pub struct Prediction {
+    pub id: Option<String>,
+    /// The classes above their threshold, from the highest score to the lowest.
+    pub labels: Vec<String>,
+    /// The score of every class, ordered by class index. `None` when the text cannot be
+    /// classified, e.g. when it is empty.
+    pub scores: Option<Vec<f32>>,
+}
+
+/// A line of the JSONL file. The id is written under the name of the id column.
+#[derive(Serialize)]
+struct JsonlPrediction<'a> {
+    #[serde(flatten)]
+    id: Option<BTreeMap<&'a str, &'a Option<String>>>,
+    labels: &'a [String],
+    scores: Option<BTreeMap<&'a str, f32>>,
+}
+
+/// Writes predictions to a CSV, JSONL or Parquet file, a batch at a time.
+///
+/// Besides the id and the labels, every class gets its own score column, named `score_<class>`
+/// in the CSV and Parquet files and keyed by class in the `scores` object of the JSONL file.
+pub struct PredictionWriter {
+    sink: Sink,
+    id_column: Option<String>,
+    class_names: Vec<String>,
+}
+
+enum Sink {
+    Csv(Box<csv::Writer<File>>),
+    Jsonl(BufWriter<File>),
+    Parquet(Box<BatchedWriter<File>>),
+}
+
+impl PredictionWriter {
+    /// Create the file at `path`, and write its header when the format has one.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file of predictions.
+    /// * `format` - The format of the file.
+    /// * `id_column` - The name of the id column, when the predictions have ids.
+    /// * `class_names` - The names of the classes, ordered by class index.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the file cannot be created.
+    pub fn create(
+        path: &str,
+        format: OutputFormat,
+        id_column: Option<&str>,
+        class_names: Vec<String>,
+    ) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path))?;
+        let score_columns = class_names.iter().map(|class| format!("score_{}", class));
+
+        let sink = match format {
+            OutputFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(file);
+                let header = id_column
+                    .map(str::to_string)
+                    .into_iter()
+                    .chain(["labels".to_string()])
+                    .chain(score_columns);
+                writer.write_record(header)?;
+                Sink::Csv(Box::new(writer))
+            }
+            OutputFormat::Jsonl => Sink::Jsonl(BufWriter::new(file)),
+            OutputFormat::Parquet => {
+                let fields = id_column
+                    .map(|column| Field::new(column, DataType::String))
+                    .into_iter()
+                    .chain([Field::new("labels", DataType::String)])
+                    .chain(score_columns.map(|column| Field::new(&column, DataType::Float32)));
+                let schema = Schema::from_iter(fields);
+                Sink::Parquet(Box::new(ParquetWriter::new(file).batched(&schema)?))
+            }
+        };
+
+        Ok(Self {
+            sink,
+            id_column: id_column.map(str::to_string),
+            class_names,
+        })
+    }
+
+    /// Append the predictions of a batch of texts.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the predictions cannot be written.
+    pub fn write_batch(&mut self, predictions: &[Prediction]) -> Result<()> {
+        match &mut self.sink {
+            Sink::Csv(writer) => {
+                for prediction in predictions {
+                    let scores = (0..self.class_names.len()).map(|index| {
+                        prediction
+                            .scores
+                            .as_ref()
+                            .map(|scores| scores[index].to_string())
+                            .unwrap_or_default()
+                    });
+                    let record = self
+                        .id_column
+                        .as_ref()
+                        .map(|_| prediction.id.clone().unwrap_or_default())
+                        .into_iter()
+                        .chain([prediction.labels.join(LABEL_SEPARATOR)])
+                        .chain(scores);
+                    writer.write_record(record)?;
+                }
+            }
+            Sink::Jsonl(writer) => {
+                for prediction in predictions {
+                    let line = JsonlPrediction {
+                        id: self
+                            .id_column
+                            .as_deref()
+                            .map(|column| BTreeMap::from([(column, &prediction.id)])),
+                        labels: &prediction.labels,
+                        scores: prediction.scores.as_ref().map(|scores| {
+                            self.class_names
+                                .iter()
+                                .map(String::as_str)
+                                .zip(scores.iter().copied())
+                                .collect()
+                        }),
+                    };
+
+                    serde_json::to_writer(&mut *writer, &line)?;
+                    writer.write_all(b"\n")?;
+                }
+            }
+            Sink::Parquet(writer) => {
+                let mut columns = Vec::with_capacity(self.class_names.len() + 2);
+                if let Some(id_column) = &self.id_column {
+                    let ids: Vec<Option<String>> = predictions
+                        .iter()
+                        .map(|prediction| prediction.id.clone())
+                        .collect();
+                    columns.push(Series::new(id_column, ids));
+                }
+                let labels: Vec<String> = predictions
+                    .iter()
+                    .map(|prediction| prediction.labels.join(LABEL_SEPARATOR))
+                    .collect();
+                columns.push(Series::new("labels", labels));
+                for (index, class) in self.class_names.iter().enumerate() {
+                    let scores: Vec<Option<f32>> = predictions
+                        .iter()
+                        .map(|prediction| prediction.scores.as_ref().map(|scores| scores[index]))
+                        .collect();
+                    columns.push(Series::new(&format!("score_{}", class), scores));
+                }
+
+                writer.write_batch(&DataFrame::new(columns)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flush the predictions, and write the footer of a Parquet file.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the file cannot be written.
+    pub fn finish(self) -> Result<()> {
+        match self.sink {
+            Sink::Csv(mut writer) => writer.flush()?,
+            Sink::Jsonl(mut writer) => writer.flush()?,
+            Sink::Parquet(mut writer) => {
+                writer.finish()?;
+            }
+        }
+        Ok(())
+    }
+}
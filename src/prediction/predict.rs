@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use common::classify::predict_batch;
+use common::input::RecordReader;
+use common::loader::load_shared_data;
+use common::monitoring::ServingMetrics;
+use common::output::PredictionWriter;
+use common::prediction_config::Args;
+use common::types::PredictionOptions;
+
+pub fn main() -> Result<()> {
+    let args = Args::parse();
+
+    match &args.log_level {
+        Some(filters) => env_logger::Builder::new().parse_filters(filters).init(),
+        None => env_logger::init(),
+    }
+
+    let options = PredictionOptions {
+        threshold: args.threshold,
+        ..Default::default()
+    };
+    options.validate()?;
+    anyhow::ensure!(args.batch_size > 0, "The batch size must be at least 1");
+
+    // Load the artifacts the same way the server does
+    let paths = args.artifact_paths();
+    log::info!("Loading the model artifacts from {:?}", paths);
+    let data = load_shared_data(&paths).context("Failed to load the model artifacts")?;
+
+    let class_names = (0..data.index_to_class.len() as u32)
+        .map(|index| {
+            data.index_to_class
+                .get(&index)
+                .cloned()
+                .with_context(|| format!("No class has the index {}", index))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut reader = RecordReader::open(
+        &args.input,
+        args.input_format()?,
+        &args.text_column,
+        args.id_column.as_deref(),
+    )?;
+    let mut writer = PredictionWriter::create(
+        &args.output,
+        args.output_format()?,
+        args.id_column.as_deref(),
+        class_names,
+    )?;
+
+    // Only used to reuse the prediction code of the server, the metrics are not exported
+    let metrics = ServingMetrics::new()?;
+
+    let mut n_texts = 0;
+    loop {
+        let records = reader.next_batch(args.batch_size)?;
+        if records.is_empty() {
+            break;
+        }
+        n_texts += records.len();
+
+        let predictions = predict_batch(records, &data, &options, &metrics)?;
+        writer.write_batch(&predictions)?;
+        log::debug!("Classified {} texts", n_texts);
+    }
+    writer.finish()?;
+
+    log::info!(
+        "Wrote the predictions of {} texts to {}",
+        n_texts,
+        args.output
+    );
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use super::error::ServingError;
+use std::{future::Future, sync::Arc, time::Duration};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+
+type Job<I, O> = (I, oneshot::Sender<Result<O, ServingError>>);
+
+/// Coalesces inputs submitted concurrently into batches, so that many single predictions share one
+/// forward pass.
+///
+/// A batch is closed as soon as it holds `max_batch_size` inputs or `max_wait` has passed since its
+/// first input arrived, whichever comes first. Each batch is handed to the handler in its own task,
+/// and every output is sent back to the caller that submitted the matching input.
+pub struct MicroBatcher<I, O> {
+    sender: mpsc::Sender<Job<I, O>>,
+}
+
+impl<I, O> Clone for MicroBatcher<I, O> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<I, O> MicroBatcher<I, O>
+where
+    I: Send + 'static,
+    O: Send + 'static,
+{
+    /// Start the batching task. Must be called from within a tokio runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_batch_size` - The maximum number of inputs in a batch.
+    /// * `max_wait` - How long the first input of a batch waits for more inputs to arrive.
+    /// * `capacity` - The maximum number of inputs waiting to be batched.
+    /// * `handler` - Computes the outputs of a batch, in the order of its inputs.
+    pub fn new<F, Fut>(
+        max_batch_size: usize,
+        max_wait: Duration,
+        capacity: usize,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(Vec<I>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Vec<O>, ServingError>> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        tokio::spawn(collect_batches(
+            receiver,
+            max_batch_size.max(1),
+            max_wait,
+            Arc::new(handler),
+        ));
+        Self { sender }
+    }
+
+    /// Submit an input and wait for the output of the batch it ends up in.
+    ///
+    /// # Errors
+    ///
+    /// This function returns `ServingError::Unavailable` if too many inputs are already waiting, or the
+    /// error the handler returned for the batch.
+    pub async fn submit(&self, input: I) -> Result<O, ServingError> {
+        let (sender, receiver) = oneshot::channel();
+        self.sender.try_send((input, sender)).map_err(|_| {
+            ServingError::Unavailable(
+                "The server is overloaded, too many predictions are waiting to be batched"
+                    .to_string(),
+            )
+        })?;
+
+        receiver.await.map_err(|_| {
+            ServingError::Model("The batch was dropped before it completed".to_string())
+        })?
+    }
+}
+
+async fn collect_batches<I, O, F, Fut>(
+    mut receiver: mpsc::Receiver<Job<I, O>>,
+    max_batch_size: usize,
+    max_wait: Duration,
+    handler: Arc<F>,
+) where
+    I: Send + 'static,
+    O: Send + 'static,
+    F: Fn(Vec<I>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Vec<O>, ServingError>> + Send + 'static,
+{
+    while let Some(first) = receiver.recv().await {
+        let mut batch = vec![first];
+        let deadline = Instant::now() + max_wait;
+
+        while batch.len() < max_batch_size {
+            match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(job)) => batch.push(job),
+                // Either the deadline passed or all the senders are gone
+                _ => break,
+            }
+        }
+
+        log::debug!("Running a batch of {} predictions", batch.len());
+        let handler = Arc::clone(&handler);
+        tokio::spawn(async move {
+            let (inputs, senders): (Vec<I>, Vec<_>) = batch.into_iter().unzip();
+            let n_inputs = inputs.len();
+
+            match handler(inputs).await {
+                Ok(outputs) if outputs.len() == n_inputs => {
+                    for (sender, output) in senders.into_iter().zip(outputs) {
+                        // The caller may have gone away, e.g. if the client disconnected
+                        let _ = sender.send(Ok(output));
+                    }
+                }
+                Ok(outputs) => {
+                    let error = ServingError::Model(format!(
+                        "Expected {} outputs for the batch, got {}",
+                        n_inputs,
+                        outputs.len()
+                    ));
+                    for sender in senders {
+                        let _ = sender.send(Err(error.clone()));
+                    }
+                }
+                Err(error) => {
+                    for sender in senders {
+                        let _ = sender.send(Err(error.clone()));
+                    }
+                }
+            }
+        });
+    }
+}
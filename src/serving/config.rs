@@ -0,0 +1,96 @@
+use super::registry::BatchingConfig;
+use crate::ArtifactPaths;
+use clap::Parser;
+use std::{net::IpAddr, time::Duration};
+
+/// Serve headline classification predictions over HTTP.
+///
+/// Every option can also be set through the environment variable shown in its help.
+#[derive(Parser, Debug)]
+#[command(version)]
+pub struct Args {
+    /// Address the server binds to. Use 0.0.0.0 to accept connections from outside a container.
+    #[arg(long, env = "INFERENCE_HOST", default_value = "127.0.0.1")]
+    pub host: IpAddr,
+
+    #[arg(long, env = "INFERENCE_PORT", default_value_t = 3030)]
+    pub port: u16,
+
+    /// Directory holding the model weights, model config, vocabulary and label mapping. When not
+    /// set, the artifacts are loaded from `model/`.
+    #[arg(long, env = "INFERENCE_MODEL_DIR")]
+    pub model_dir: Option<String>,
+
+    /// Directory of additional models served under `/models`, laid out as `<name>/<version>/`.
+    #[arg(long, env = "INFERENCE_MODELS_DIR")]
+    pub models_dir: Option<String>,
+
+    /// Maximum number of texts accepted by the batch prediction endpoint.
+    #[arg(long, env = "INFERENCE_MAX_BATCH_SIZE", default_value_t = 256)]
+    pub max_batch_size: usize,
+
+    /// Maximum number of predictions computed at the same time. Defaults to the number of CPUs.
+    #[arg(
+        long,
+        env = "INFERENCE_MAX_CONCURRENCY",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub max_concurrency: Option<usize>,
+
+    /// Maximum number of predictions waiting for a free slot. Requests beyond it get a 503.
+    #[arg(long, env = "INFERENCE_QUEUE_DEPTH", default_value_t = 64)]
+    pub queue_depth: usize,
+
+    /// Maximum number of concurrent `/predict` requests coalesced into one forward pass. Use 1 to
+    /// disable batching.
+    #[arg(long, env = "INFERENCE_MICRO_BATCH_SIZE", default_value_t = 16)]
+    pub micro_batch_size: usize,
+
+    /// How long, in milliseconds, a `/predict` request waits for others to share its forward pass.
+    #[arg(long, env = "INFERENCE_MICRO_BATCH_WAIT_MS", default_value_t = 2)]
+    pub micro_batch_wait_ms: u64,
+
+    /// Token expected in the `Authorization: Bearer <token>` header of `POST /admin/reload`. The
+    /// endpoint is disabled when not set.
+    #[arg(long, env = "INFERENCE_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+
+    /// When set, the artifacts are checked for changes every this many seconds and reloaded.
+    #[arg(
+        long,
+        env = "INFERENCE_RELOAD_INTERVAL_SECS",
+        value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(1..)
+    )]
+    pub reload_interval_secs: Option<u64>,
+
+    /// Log filter, e.g. `info` or `inference=debug`. Falls back to `RUST_LOG` when not set.
+    #[arg(long, env = "INFERENCE_LOG_LEVEL")]
+    pub log_level: Option<String>,
+}
+
+impl Args {
+    pub fn max_concurrency(&self) -> usize {
+        self.max_concurrency.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+    }
+
+    /// How the single predictions are batched. Every slot of the pool, running or queued, can
+    /// wait for a full batch.
+    pub fn batching_config(&self) -> BatchingConfig {
+        BatchingConfig {
+            micro_batch_size: self.micro_batch_size,
+            micro_batch_wait: Duration::from_millis(self.micro_batch_wait_ms),
+            queue_capacity: (self.max_concurrency() + self.queue_depth) * self.micro_batch_size,
+        }
+    }
+
+    pub fn artifact_paths(&self) -> ArtifactPaths {
+        match &self.model_dir {
+            Some(directory) => ArtifactPaths::in_directory(directory),
+            None => ArtifactPaths::default(),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use serde::Serialize;
+use std::{convert::Infallible, fmt};
+use warp::{
+    body::BodyDeserializeError,
+    http::StatusCode,
+    reject::{MethodNotAllowed, PayloadTooLarge, Reject, UnsupportedMediaType},
+    Rejection, Reply,
+};
+
+/// Errors returned by the prediction endpoints.
+#[derive(Debug, Clone)]
+pub enum ServingError {
+    /// The request body is not valid JSON or does not match the expected schema.
+    BadRequest(String),
+    /// The request is well-formed but its content cannot be processed, e.g. an empty text.
+    Validation(String),
+    /// A text does not produce any token once tokenized.
+    Tokenization(String),
+    /// The batch holds more items than the server accepts.
+    BatchTooLarge { size: usize, max_size: usize },
+    /// The input tensor could not be built or the outputs could not be read.
+    Tensor(String),
+    /// The forward pass of the model failed.
+    Model(String),
+    /// The server cannot handle the request at the moment.
+    Unavailable(String),
+    /// The request is missing valid admin credentials.
+    Unauthorized(String),
+    /// No model is registered under the requested name and version.
+    ModelNotFound(String),
+    /// The model artifacts could not be reloaded. The previous ones are still served.
+    Reload(String),
+}
+
+impl ServingError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ServingError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ServingError::Validation(_) | ServingError::Tokenization(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ServingError::BatchTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            ServingError::Tensor(_) | ServingError::Model(_) | ServingError::Reload(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            ServingError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ServingError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ServingError::ModelNotFound(_) => StatusCode::NOT_FOUND,
+        }
+    }
+
+    /// A stable, machine-readable identifier of the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ServingError::BadRequest(_) => "bad_request",
+            ServingError::Validation(_) => "validation_error",
+            ServingError::Tokenization(_) => "tokenization_error",
+            ServingError::BatchTooLarge { .. } => "batch_too_large",
+            ServingError::Tensor(_) => "tensor_error",
+            ServingError::Model(_) => "model_error",
+            ServingError::Unavailable(_) => "unavailable",
+            ServingError::Unauthorized(_) => "unauthorized",
+            ServingError::ModelNotFound(_) => "model_not_found",
+            ServingError::Reload(_) => "reload_error",
+        }
+    }
+}
+
+impl fmt::Display for ServingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServingError::BadRequest(message)
+            | ServingError::Validation(message)
+            | ServingError::Tokenization(message)
+            | ServingError::Tensor(message)
+            | ServingError::Model(message)
+            | ServingError::Unavailable(message)
+            | ServingError::Unauthorized(message)
+            | ServingError::ModelNotFound(message)
+            | ServingError::Reload(message) => write!(f, "{}", message),
+            ServingError::BatchTooLarge { size, max_size } => write!(
+                f,
+                "Batch of {} items exceeds the maximum batch size of {}",
+                size, max_size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ServingError {}
+
+impl Reject for ServingError {}
+
+impl From<candle_core::Error> for ServingError {
+    fn from(err: candle_core::Error) -> Self {
+        ServingError::Tensor(err.to_string())
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ErrorDetail {
+    pub code: String,
+    pub message: String,
+}
+
+impl From<&ServingError> for ErrorDetail {
+    fn from(error: &ServingError) -> Self {
+        Self {
+            code: error.code().to_string(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// The body of every error response.
+#[derive(Serialize, Debug)]
+pub struct ErrorResponse {
+    pub error: ErrorDetail,
+}
+
+fn error_reply(status: StatusCode, code: &str, message: String) -> warp::reply::Response {
+    let body = ErrorResponse {
+        error: ErrorDetail {
+            code: code.to_string(),
+            message,
+        },
+    };
+    warp::reply::with_status(warp::reply::json(&body), status).into_response()
+}
+
+impl Reply for ServingError {
+    fn into_response(self) -> warp::reply::Response {
+        error_reply(self.status_code(), self.code(), self.to_string())
+    }
+}
+
+/// Convert the rejections of the routes into JSON error responses with a matching status code.
+///
+/// # Arguments
+///
+/// * `rejection` - The rejection produced by a route, either a `ServingError` or one of warp's own rejections.
+///
+/// # Returns
+///
+/// This function always succeeds with a reply holding an `ErrorResponse` body.
+pub async fn handle_rejection(rejection: Rejection) -> Result<warp::reply::Response, Infallible> {
+    let response = if let Some(error) = rejection.find::<ServingError>() {
+        if error.status_code().is_server_error() {
+            log::error!("Failed to serve a request: {}", error);
+        }
+        error_reply(error.status_code(), error.code(), error.to_string())
+    } else if rejection.is_not_found() {
+        error_reply(StatusCode::NOT_FOUND, "not_found", "Not found".to_string())
+    } else if let Some(error) = rejection.find::<BodyDeserializeError>() {
+        let error = ServingError::BadRequest(error.to_string());
+        error_reply(error.status_code(), error.code(), error.to_string())
+    } else if let Some(error) = rejection.find::<PayloadTooLarge>() {
+        error_reply(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "payload_too_large",
+            error.to_string(),
+        )
+    } else if let Some(error) = rejection.find::<UnsupportedMediaType>() {
+        error_reply(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "unsupported_media_type",
+            error.to_string(),
+        )
+    } else if let Some(error) = rejection.find::<MethodNotAllowed>() {
+        error_reply(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "method_not_allowed",
+            error.to_string(),
+        )
+    } else {
+        log::error!("Unhandled rejection: {:?}", rejection);
+        error_reply(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+            "Internal server error".to_string(),
+        )
+    };
+
+    Ok(response)
+}
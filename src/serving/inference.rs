@@ -1,82 +1,274 @@
-use anyhow::{anyhow, Error};
-use candle_core::{Device, Tensor};
+use super::error::ServingError;
+use super::monitoring::ServingMetrics;
+use super::types::{LabelScore, PredictResponseV1, PredictionOptions, TokenStats};
+use crate::{
+    encode_texts, max_sequence_words, padding_mask, SpecialTokens, TextClassifier, Tokenizer,
+    PREDICTION_THRESHOLD,
+};
 use candle_nn::ops::sigmoid;
-use common::{map_to_indices, pad_vector, HeadlineClassifierModel, MAX_SEQ_LEN};
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Instant};
 
+/// Check that a text can be classified, i.e. that it is not empty and produces at least one token.
+///
+/// # Arguments
+///
+/// * `text` - The input text.
+/// * `index` - The position of the text in the request, reported in the error.
+/// * `tokenizer` - The tokenizer the vocabulary was built with.
+///
+/// # Errors
+///
+/// This function returns `ServingError::Validation` for an empty text and `ServingError::Tokenization` for a text without tokens.
+pub fn validate_text(
+    text: &str,
+    index: usize,
+    tokenizer: &dyn Tokenizer,
+) -> Result<(), ServingError> {
+    if text.trim().is_empty() {
+        return Err(ServingError::Validation(format!(
+            "The text of item {} is empty",
+            index
+        )));
+    }
+    if tokenizer.tokenize(text).is_empty() {
+        return Err(ServingError::Tokenization(format!(
+            "The text of item {} does not contain any token",
+            index
+        )));
+    }
+    Ok(())
+}
 
-/// Get predictions from a headline classification model for the given text.
+/// Check that every text can be classified, i.e. that it is not empty and produces at least one token.
 ///
 /// # Arguments
 ///
-/// * `text` - A string containing the input text for which predictions are to be generated.
-/// * `word_to_index` - A reference to a HashMap<String, u32> mapping words to their corresponding indices.
-/// * `model` - A reference to a HeadlineClassifierModel used for making predictions.
+/// * `texts` - A slice of strings containing the input texts.
+/// * `tokenizer` - The tokenizer the vocabulary was built with.
 ///
 /// # Errors
 ///
-/// This function can return an error if there are issues with tokenization, index mapping, tensor conversion, model inference, or sigmoid transformation.
+/// This function returns the error of the first text that cannot be classified, see `validate_text`.
+pub fn validate_texts(texts: &[String], tokenizer: &dyn Tokenizer) -> Result<(), ServingError> {
+    texts
+        .iter()
+        .enumerate()
+        .try_for_each(|(index, text)| validate_text(text, index, tokenizer))
+}
+
+/// Count the tokens of a text the model sees, and how many of them are unknown.
+///
+/// # Arguments
+///
+/// * `text` - The input text.
+/// * `tokenizer` - The tokenizer the vocabulary was built with.
+/// * `word_to_index` - A reference to a HashMap<String, u32> mapping words to their corresponding indices.
+/// * `special_tokens` - The reserved tokens the word-to-index mapping was created with.
+/// * `max_seq_len` - The sequence length the model was trained with.
 ///
 /// # Returns
 ///
-/// This function returns a `Result<Vec<f32>, Error>`, where `Vec<f32>` represents the predicted probabilities for each class on success, and `Error` represents any encountered errors.
-pub fn get_predictions(
+/// The counts of the tokens kept by `encode_sequence`, and whether the text was truncated.
+pub fn token_stats(
     text: &str,
+    tokenizer: &dyn Tokenizer,
     word_to_index: &HashMap<String, u32>,
-    model: &HeadlineClassifierModel,
-) -> Result<Vec<f32>, Error> {
-    let words = text
-        .split_whitespace()
-        .map(|s| s.to_string())
-        .collect::<Vec<String>>();
+    special_tokens: &SpecialTokens,
+    max_seq_len: usize,
+) -> TokenStats {
+    let max_words = max_sequence_words(word_to_index, special_tokens, max_seq_len);
 
-    let indices = map_to_indices(words, word_to_index);
+    let words = tokenizer.tokenize(text);
+    let used = &words[..words.len().min(max_words)];
 
-    let padded_indices: Vec<u32> = pad_vector(indices, MAX_SEQ_LEN, 0);
+    TokenStats {
+        n_tokens: used.len(),
+        n_unknown_tokens: used
+            .iter()
+            .filter(|word| !word_to_index.contains_key(*word))
+            .count(),
+        truncated: words.len() > max_words,
+    }
+}
 
-    let padded_indices_array: [u32; MAX_SEQ_LEN] = match padded_indices.try_into() {
-        Ok(array) => Ok(array),
-        Err(_) => Err(anyhow!("Failed to convert Vec<u32> to [u32; _]")),
-    }?;
+/// Get predictions from a headline classification model for a batch of texts, in a single forward pass.
+///
+/// # Arguments
+///
+/// * `texts` - A slice of strings containing the input texts for which predictions are to be generated.
+/// * `tokenizer` - The tokenizer the vocabulary was built with, used to split the texts into words.
+/// * `word_to_index` - A reference to a HashMap<String, u32> mapping words to their corresponding indices.
+/// * `special_tokens` - The reserved tokens the word-to-index mapping was created with.
+/// * `model` - The classifier used for making predictions, of any architecture.
+/// * `max_seq_len` - The sequence length the model was trained with.
+/// * `metrics` - The metrics the batch size and the time spent in tokenization and in the forward pass are recorded in.
+///
+/// # Errors
+///
+/// This function returns a `ServingError` if a text is empty or has no tokens, or if there are issues with tensor conversion, model inference, or sigmoid transformation.
+///
+/// # Returns
+///
+/// This function returns a `Result<Vec<Vec<f32>>, ServingError>` holding the predicted probabilities for each class of each text, in the order of `texts`.
+pub fn get_batch_predictions(
+    texts: &[String],
+    tokenizer: &dyn Tokenizer,
+    word_to_index: &HashMap<String, u32>,
+    special_tokens: &SpecialTokens,
+    model: &dyn TextClassifier,
+    max_seq_len: usize,
+    metrics: &ServingMetrics,
+) -> Result<Vec<Vec<f32>>, ServingError> {
+    if texts.is_empty() {
+        return Ok(Vec::new());
+    }
 
-    let tensor_indices = Tensor::new(&padded_indices_array, &Device::Cpu)?;
+    validate_texts(texts, tokenizer)?;
+    metrics.observe_batch_size(texts.len());
 
-    let predictions = model.forward(&tensor_indices)?;
+    let started = Instant::now();
+    // Build the inputs on the device the weights were loaded on, e.g. a GPU when one is available
+    let tensor_indices = encode_texts(
+        texts,
+        tokenizer,
+        word_to_index,
+        special_tokens,
+        max_seq_len,
+        &model.config().device,
+    )
+    .map_err(|error| ServingError::Tensor(error.to_string()))?;
+    metrics.observe_tokenization(started.elapsed());
 
-    let predictions_vec = sigmoid(&predictions)?.flatten(0, 1)?.to_vec1()?;
+    let started = Instant::now();
+    // The model returns (n_texts, n_classes) logits
+    let predictions = padding_mask(&tensor_indices)
+        .and_then(|mask| model.forward(&tensor_indices, &mask))
+        .map_err(|error| ServingError::Model(error.to_string()))?;
+
+    let predictions_vec = sigmoid(&predictions)?.to_vec2()?;
+    metrics.observe_forward(started.elapsed());
 
     Ok(predictions_vec)
 }
 
-/// Map logits to class names with scores based on a given threshold.
+/// Score every class of a text, from the highest score to the lowest.
 ///
 /// # Arguments
 ///
-/// * `logits` - A vector of f32 representing the logits or scores for each class.
+/// * `logits` - A slice of f32 representing the logits or scores for each class.
 /// * `index_to_class` - A reference to a HashMap<u32, String> mapping class indices to their corresponding names.
-/// * `threshold` - A threshold value used to filter out class names with logits below this value.
+/// * `thresholds` - The threshold of every class, ordered by class index, used unless the request sets its own threshold.
+/// * `options` - The options of the request.
 ///
 /// # Returns
 ///
-/// This function returns a vector of HashMaps, where each HashMap associates class names (String) with their corresponding scores (f32).
-pub fn map_to_class_names_with_scores(
-    logits: Vec<f32>,
+/// Every class with its score, flagged when the score is at or above the threshold of the class,
+/// as during training.
+pub fn score_labels(
+    logits: &[f32],
     index_to_class: &HashMap<u32, String>,
-    threshold: f32,
-) -> Vec<HashMap<String, f32>> {
-    let mut class_names_with_logits: Vec<HashMap<String, f32>> = Vec::new();
-
-    for (index, &logit) in logits.iter().enumerate() {
-        if logit > threshold {
-            if let Some(class_name) = index_to_class.get(&(index as u32)) {
-                let mapping = vec![(class_name, logit)]
-                    .into_iter()
-                    .map(|(c, l)| (c.to_string(), l))
-                    .collect();
-                class_names_with_logits.push(mapping);
-            }
-        }
+    thresholds: &[f32],
+    options: &PredictionOptions,
+) -> Vec<LabelScore> {
+    let mut labels: Vec<LabelScore> = logits
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &score)| {
+            let threshold = options
+                .threshold
+                .or_else(|| thresholds.get(index).copied())?;
+            index_to_class
+                .get(&(index as u32))
+                .map(|class_name| LabelScore {
+                    label: class_name.clone(),
+                    score,
+                    above_threshold: score >= threshold,
+                })
+        })
+        .collect();
+    labels.sort_by(|a, b| b.score.total_cmp(&a.score));
+    labels
+}
+
+/// Select the classes returned for a text according to the options of the request.
+///
+/// # Arguments
+///
+/// * `labels` - Every class of the text, as returned by `score_labels`.
+/// * `options` - The top-k and all-scores options of the request.
+///
+/// # Returns
+///
+/// Every class when `include_all_scores` is set, else the `top_k` first classes when it is set,
+/// else the classes above their threshold.
+pub fn select_labels(labels: Vec<LabelScore>, options: &PredictionOptions) -> Vec<LabelScore> {
+    match (options.include_all_scores, options.top_k) {
+        (true, _) => labels,
+        (false, Some(k)) => labels.into_iter().take(k).collect(),
+        (false, None) => labels
+            .into_iter()
+            .filter(|label| label.above_threshold)
+            .collect(),
     }
+}
 
-    class_names_with_logits
+/// Build the response of the `/v1` routes as the server returned it before the per-class
+/// thresholds: one single-entry map per class scoring strictly above the threshold, in the order of
+/// the class indices.
+///
+/// # Arguments
+///
+/// * `scores` - The probability of every class, ordered by class index.
+/// * `index_to_class` - A reference to a HashMap<u32, String> mapping class indices to their corresponding names.
+/// * `options` - The options of the request. `threshold` replaces the fixed `PREDICTION_THRESHOLD`,
+///   and `top_k` returns the `top_k` highest-scoring classes instead, still in the order of the
+///   class indices.
+pub fn to_v1_response(
+    scores: &[f32],
+    index_to_class: &HashMap<u32, String>,
+    options: &PredictionOptions,
+) -> PredictResponseV1 {
+    let classes: Vec<(usize, &String, f32)> = scores
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &score)| {
+            index_to_class
+                .get(&(index as u32))
+                .map(|class_name| (index, class_name, score))
+        })
+        .collect();
+
+    let mut selected: Vec<(usize, &String, f32)> = match options.top_k {
+        Some(k) => {
+            let mut by_score = classes.clone();
+            by_score.sort_by(|a, b| b.2.total_cmp(&a.2));
+            by_score.truncate(k);
+            by_score
+        }
+        None => {
+            let threshold = options.threshold.unwrap_or(PREDICTION_THRESHOLD);
+            classes
+                .iter()
+                .filter(|(_, _, score)| *score > threshold)
+                .copied()
+                .collect()
+        }
+    };
+    selected.sort_by_key(|(index, _, _)| *index);
+
+    let predictions = selected
+        .into_iter()
+        .map(|(_, class_name, score)| HashMap::from([(class_name.clone(), score)]))
+        .collect();
+    let scores = options.include_all_scores.then(|| {
+        classes
+            .iter()
+            .map(|(_, class_name, score)| ((*class_name).clone(), *score))
+            .collect()
+    });
+
+    PredictResponseV1 {
+        predictions,
+        scores,
+    }
 }
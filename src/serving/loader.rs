@@ -0,0 +1,72 @@
+use crate::{
+    create_vocabulary_to_index_mapping, load_index_to_class_mapping, load_model, load_model_bundle,
+    load_vocabulary, ArtifactPaths, DefaultTokenizer, ModelBundle, ModelConfig, SpecialTokens,
+    TextClassifier,
+};
+use candle_core::Device;
+use std::collections::HashMap;
+
+/// The artifacts of a training run, as used to make predictions.
+pub struct SharedData {
+    pub tokenizer: DefaultTokenizer,
+    pub word_to_index: HashMap<String, u32>,
+    pub special_tokens: SpecialTokens,
+    pub index_to_class: HashMap<u32, String>,
+    pub model: Box<dyn TextClassifier>,
+    pub max_seq_len: usize,
+    /// The decision threshold of every class, ordered by class index.
+    pub thresholds: Vec<f32>,
+    /// The configuration and metadata of the training run.
+    pub bundle: ModelBundle,
+}
+
+/// Load the artifacts of a training run and build the data used to make predictions.
+///
+/// # Arguments
+///
+/// * `paths` - A reference to the locations of the artifacts.
+///
+/// # Errors
+///
+/// This function returns an error if an artifact cannot be loaded or if the artifacts do not belong to the same training run.
+pub fn load_shared_data(paths: &ArtifactPaths) -> anyhow::Result<SharedData> {
+    // Load the configuration the model was trained with
+    let bundle = load_model_bundle(&paths.model_config)?;
+
+    // Load the vocabulary and the mappings
+    let vocabulary = load_vocabulary(&paths.vocabulary)?;
+    let index_to_class = load_index_to_class_mapping(&paths.index_to_class)?;
+
+    // Fail early if the artifacts on disk were not produced by the same training run
+    bundle.validate(&vocabulary, &index_to_class)?;
+
+    // Rebuild the tokenizer the vocabulary was created with
+    let tokenizer = DefaultTokenizer::new(vocabulary.tokenizer);
+
+    let word_to_index =
+        create_vocabulary_to_index_mapping(&vocabulary.vocabulary, &vocabulary.special_tokens);
+
+    let model_config = ModelConfig {
+        device: Device::cuda_if_available(0)?,
+        ..bundle.model.clone()
+    };
+
+    // Build the model described by the config and load its weights
+    let model = load_model(&paths.model, &model_config)?;
+    log::info!(
+        "Loaded a {:?} model with {} parameters",
+        model_config.architecture,
+        model.n_parameters()
+    );
+
+    Ok(SharedData {
+        tokenizer,
+        word_to_index,
+        special_tokens: vocabulary.special_tokens,
+        index_to_class,
+        model,
+        max_seq_len: model_config.max_seq_len,
+        thresholds: bundle.class_thresholds(),
+        bundle,
+    })
+}
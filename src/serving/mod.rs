@@ -1,2 +1,11 @@
-pub mod processing;
-pub mod request_model;
+pub mod batcher;
+pub mod config;
+pub mod error;
+pub mod inference;
+pub mod loader;
+pub mod monitoring;
+pub mod pool;
+pub mod registry;
+pub mod routes;
+pub mod state;
+pub mod types;
@@ -0,0 +1,203 @@
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, Opts,
+    Registry, TextEncoder,
+};
+use std::time::Duration;
+
+/// Paths reported as their own route label. Any other path is reported as "other", so that
+/// random URLs cannot blow up the number of series.
+const KNOWN_ROUTES: [&str; 8] = [
+    "/admin/reload",
+    "/hc",
+    "/metrics",
+    "/models",
+    "/predict",
+    "/predict/batch",
+    "/v1/predict",
+    "/v1/predict/batch",
+];
+
+/// The route label of a path. The routes of the registered models are reported with their
+/// placeholders, e.g. "/models/{name}/predict".
+fn route_label(path: &str) -> &'static str {
+    if let Some(route) = KNOWN_ROUTES.iter().find(|route| **route == path) {
+        return route;
+    }
+
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["models", _, "predict"] => "/models/{name}/predict",
+        ["models", _, "versions", _, "predict"] => "/models/{name}/versions/{version}/predict",
+        _ => "other",
+    }
+}
+
+/// The Prometheus metrics of the inference server.
+///
+/// All metrics are registered in a dedicated registry, rendered by `GET /metrics`.
+pub struct ServingMetrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    tokenization_duration: Histogram,
+    forward_duration: Histogram,
+    batch_size: Histogram,
+    unk_token_ratio: Histogram,
+    predictions: IntCounterVec,
+    reloads: IntCounterVec,
+}
+
+impl ServingMetrics {
+    /// Create the metrics and register them.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if a metric is invalid or registered twice.
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new();
+
+        let requests = IntCounterVec::new(
+            Opts::new(
+                "inference_http_requests_total",
+                "Number of HTTP requests, by route and status code.",
+            ),
+            &["route", "status"],
+        )?;
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "inference_request_duration_seconds",
+                "Total time spent serving a request, by route.",
+            ),
+            &["route"],
+        )?;
+        let tokenization_duration = Histogram::with_opts(
+            HistogramOpts::new(
+                "inference_tokenization_duration_seconds",
+                "Time spent tokenizing and encoding the texts of a batch.",
+            )
+            .buckets(exponential_buckets(0.00001, 4.0, 10)?),
+        )?;
+        let forward_duration = Histogram::with_opts(HistogramOpts::new(
+            "inference_forward_duration_seconds",
+            "Time spent in the forward pass of the model for a batch.",
+        ))?;
+        let batch_size = Histogram::with_opts(
+            HistogramOpts::new(
+                "inference_batch_size",
+                "Number of texts in each forward pass.",
+            )
+            .buckets(exponential_buckets(1.0, 2.0, 10)?),
+        )?;
+        let unk_token_ratio = Histogram::with_opts(
+            HistogramOpts::new(
+                "inference_unk_token_ratio",
+                "Fraction of the tokens of a request missing from the vocabulary.",
+            )
+            .buckets(vec![0.0, 0.05, 0.1, 0.2, 0.3, 0.5, 0.75, 1.0]),
+        )?;
+        let predictions = IntCounterVec::new(
+            Opts::new(
+                "inference_predictions_total",
+                "Number of times each class was predicted above the threshold, by model.",
+            ),
+            &["model", "class"],
+        )?;
+
+        let reloads = IntCounterVec::new(
+            Opts::new(
+                "inference_reloads_total",
+                "Number of reloads of the model artifacts, by result.",
+            ),
+            &["result"],
+        )?;
+
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(request_duration.clone()))?;
+        registry.register(Box::new(tokenization_duration.clone()))?;
+        registry.register(Box::new(forward_duration.clone()))?;
+        registry.register(Box::new(batch_size.clone()))?;
+        registry.register(Box::new(unk_token_ratio.clone()))?;
+        registry.register(Box::new(predictions.clone()))?;
+        registry.register(Box::new(reloads.clone()))?;
+
+        Ok(Self {
+            registry,
+            requests,
+            request_duration,
+            tokenization_duration,
+            forward_duration,
+            batch_size,
+            unk_token_ratio,
+            predictions,
+            reloads,
+        })
+    }
+
+    /// Record a served request.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the request.
+    /// * `status` - The status code of the response.
+    /// * `elapsed` - The time spent serving the request.
+    pub fn observe_request(&self, path: &str, status: u16, elapsed: Duration) {
+        let route = route_label(path);
+
+        self.requests
+            .with_label_values(&[route, &status.to_string()])
+            .inc();
+        self.request_duration
+            .with_label_values(&[route])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_tokenization(&self, elapsed: Duration) {
+        self.tokenization_duration.observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_forward(&self, elapsed: Duration) {
+        self.forward_duration.observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_batch_size(&self, size: usize) {
+        self.batch_size.observe(size as f64);
+    }
+
+    pub fn observe_unk_token_ratio(&self, ratio: f64) {
+        self.unk_token_ratio.observe(ratio);
+    }
+
+    /// Count the classes predicted for a text.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - The name of the model that made the predictions.
+    /// * `class_names` - The classes scored above their threshold.
+    pub fn observe_predictions<'a>(
+        &self,
+        model: &str,
+        class_names: impl IntoIterator<Item = &'a str>,
+    ) {
+        for class_name in class_names {
+            self.predictions
+                .with_label_values(&[model, class_name])
+                .inc();
+        }
+    }
+
+    pub fn observe_reload(&self, succeeded: bool) {
+        let result = if succeeded { "success" } else { "failure" };
+        self.reloads.with_label_values(&[result]).inc();
+    }
+
+    /// Render all the metrics in the Prometheus text exposition format.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the metrics cannot be encoded.
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        String::from_utf8(buffer).map_err(|error| prometheus::Error::Msg(error.to_string()))
+    }
+}
@@ -0,0 +1,64 @@
+use super::error::ServingError;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Runs the CPU-bound model inference on tokio's blocking threads, off the async executor.
+///
+/// At most `max_concurrency` jobs run at the same time and at most `queue_depth` more wait for
+/// their turn. Jobs submitted beyond that are rejected right away with `ServingError::Unavailable`,
+/// so that an overloaded server answers quickly instead of piling up requests.
+#[derive(Clone, Debug)]
+pub struct InferencePool {
+    running: Arc<Semaphore>,
+    admitted: Arc<Semaphore>,
+}
+
+impl InferencePool {
+    /// # Errors
+    ///
+    /// This function returns an error if `max_concurrency` is 0, as no job could ever run.
+    pub fn new(max_concurrency: usize, queue_depth: usize) -> anyhow::Result<Self> {
+        if max_concurrency == 0 {
+            anyhow::bail!("The inference pool needs to run at least one job at a time");
+        }
+
+        Ok(Self {
+            running: Arc::new(Semaphore::new(max_concurrency)),
+            admitted: Arc::new(Semaphore::new(max_concurrency + queue_depth)),
+        })
+    }
+
+    /// Run `job` on a blocking thread once a slot is free.
+    ///
+    /// # Errors
+    ///
+    /// This function returns `ServingError::Unavailable` if the pool is saturated, `ServingError::Model`
+    /// if the job panicked, or the error returned by the job itself.
+    pub async fn run<F, T>(&self, job: F) -> Result<T, ServingError>
+    where
+        F: FnOnce() -> Result<T, ServingError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let admission = Arc::clone(&self.admitted)
+            .try_acquire_owned()
+            .map_err(|_| {
+                ServingError::Unavailable(
+                    "The server is overloaded, too many predictions are queued".to_string(),
+                )
+            })?;
+
+        let permit = Arc::clone(&self.running)
+            .acquire_owned()
+            .await
+            .map_err(|_| ServingError::Unavailable("The inference pool is closed".to_string()))?;
+
+        // The slots are held by the job itself, so that they are only released once it finishes,
+        // even if the request waiting for it is dropped meanwhile
+        tokio::task::spawn_blocking(move || {
+            let _slots = (admission, permit);
+            job()
+        })
+        .await
+        .map_err(|error| ServingError::Model(format!("The inference task failed: {}", error)))?
+    }
+}
@@ -0,0 +1,223 @@
+use super::batcher::MicroBatcher;
+use super::error::ServingError;
+use super::inference::get_batch_predictions;
+use super::loader::SharedData;
+use super::monitoring::ServingMetrics;
+use super::pool::InferencePool;
+use super::state::ModelState;
+use crate::ArtifactPaths;
+use anyhow::Context;
+use std::{cmp::Ordering, collections::BTreeMap, fs, path::Path, sync::Arc, time::Duration};
+
+/// Batches single texts, and returns their probabilities along with the artifacts that produced
+/// them, so that they are mapped to the right labels even if the artifacts were reloaded meanwhile.
+pub type PredictionBatcher = MicroBatcher<String, (Vec<f32>, Arc<SharedData>)>;
+
+/// How the single predictions of a model are coalesced into batches.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchingConfig {
+    /// Maximum number of texts of a batch.
+    pub micro_batch_size: usize,
+    /// How long the first text of a batch waits for others.
+    pub micro_batch_wait: Duration,
+    /// Maximum number of texts waiting for a batch. Texts beyond it are rejected.
+    pub queue_capacity: usize,
+}
+
+/// A model ready to serve predictions.
+pub struct ServedModel {
+    pub name: String,
+    /// The version, for the models of the registry.
+    pub version: Option<String>,
+    /// Identifies the model in the metrics, e.g. "sections/2".
+    pub label: String,
+    pub state: Arc<ModelState>,
+    pub batcher: PredictionBatcher,
+}
+
+impl ServedModel {
+    /// Load the artifacts of a model and start the batcher of its single predictions.
+    ///
+    /// Concurrent single predictions are coalesced into batches, each run as one job on the pool.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the artifacts cannot be loaded.
+    pub fn load(
+        name: String,
+        version: Option<String>,
+        paths: ArtifactPaths,
+        pool: &InferencePool,
+        metrics: &Arc<ServingMetrics>,
+        batching: BatchingConfig,
+    ) -> anyhow::Result<Self> {
+        let label = match &version {
+            Some(version) => format!("{}/{}", name, version),
+            None => name.clone(),
+        };
+        log::info!("Loading the artifacts of model {} from {:?}", label, paths);
+        let state = Arc::new(ModelState::load(paths, metrics.clone())?);
+
+        let batcher: PredictionBatcher = {
+            let state = state.clone();
+            let pool = pool.clone();
+            let metrics = metrics.clone();
+            MicroBatcher::new(
+                batching.micro_batch_size,
+                batching.micro_batch_wait,
+                batching.queue_capacity,
+                move |texts: Vec<String>| {
+                    let data = state.current();
+                    let pool = pool.clone();
+                    let metrics = metrics.clone();
+                    async move {
+                        pool.run(move || {
+                            let predictions = get_batch_predictions(
+                                &texts,
+                                &data.tokenizer,
+                                &data.word_to_index,
+                                &data.special_tokens,
+                                data.model.as_ref(),
+                                data.max_seq_len,
+                                &metrics,
+                            )?;
+                            Ok(predictions
+                                .into_iter()
+                                .map(|predictions| (predictions, data.clone()))
+                                .collect())
+                        })
+                        .await
+                    }
+                },
+            )
+        };
+
+        Ok(Self {
+            name,
+            version,
+            label,
+            state,
+            batcher,
+        })
+    }
+}
+
+/// Orders versions numerically when they are numbers, optionally prefixed with "v", and
+/// lexicographically otherwise. Numeric versions come after the other ones.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let key = |version: &str| {
+        (
+            version.trim_start_matches('v').parse::<u64>().ok(),
+            version.to_string(),
+        )
+    };
+    key(a).cmp(&key(b))
+}
+
+fn sub_directories(directory: &Path) -> anyhow::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(directory)
+        .with_context(|| format!("Failed to read the directory {}", directory.display()))?
+    {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    Ok(names)
+}
+
+/// The named and versioned models served under `/models`.
+#[derive(Default)]
+pub struct ModelRegistry {
+    /// The versions of each model, sorted from the oldest to the latest.
+    models: BTreeMap<String, Vec<(String, Arc<ServedModel>)>>,
+}
+
+impl ModelRegistry {
+    /// Load every model of a directory laid out as `<name>/<version>/`, each version directory
+    /// holding the artifacts of a training run.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The directory holding one sub-directory per model.
+    /// * `serve` - Builds a served model from its name, its version and the location of its artifacts.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the directory cannot be read or if a model cannot be loaded.
+    pub fn load<F>(directory: &str, mut serve: F) -> anyhow::Result<Self>
+    where
+        F: FnMut(String, Option<String>, ArtifactPaths) -> anyhow::Result<ServedModel>,
+    {
+        let mut models = BTreeMap::new();
+
+        for name in sub_directories(Path::new(directory))? {
+            let model_directory = Path::new(directory).join(&name);
+            let mut versions = Vec::new();
+
+            for version in sub_directories(&model_directory)? {
+                let version_directory = model_directory.join(&version);
+                let paths = ArtifactPaths::in_directory(&version_directory.to_string_lossy());
+                let served =
+                    serve(name.clone(), Some(version.clone()), paths).with_context(|| {
+                        format!("Failed to load version {} of model {}", version, name)
+                    })?;
+                versions.push((version, Arc::new(served)));
+            }
+
+            if versions.is_empty() {
+                log::warn!("Model {} has no version, skipping it", name);
+                continue;
+            }
+            versions.sort_by(|(a, _), (b, _)| compare_versions(a, b));
+            models.insert(name, versions);
+        }
+
+        Ok(Self { models })
+    }
+
+    /// All the models, as `(name, version, is_latest, model)`.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str, bool, &Arc<ServedModel>)> {
+        self.models.iter().flat_map(|(name, versions)| {
+            let n_versions = versions.len();
+            versions
+                .iter()
+                .enumerate()
+                .map(move |(index, (version, model))| {
+                    (
+                        name.as_str(),
+                        version.as_str(),
+                        index + 1 == n_versions,
+                        model,
+                    )
+                })
+        })
+    }
+
+    /// The model served for `name` and `version`, or for the latest version of `name` when no
+    /// version is given.
+    ///
+    /// # Errors
+    ///
+    /// This function returns `ServingError::ModelNotFound` if no such model is registered.
+    pub fn get(&self, name: &str, version: Option<&str>) -> Result<Arc<ServedModel>, ServingError> {
+        let versions = self
+            .models
+            .get(name)
+            .ok_or_else(|| ServingError::ModelNotFound(format!("No model is named {}", name)))?;
+
+        let found = match version {
+            Some(version) => versions.iter().find(|(candidate, _)| candidate == version),
+            None => versions.last(),
+        };
+
+        found.map(|(_, model)| model.clone()).ok_or_else(|| {
+            ServingError::ModelNotFound(format!(
+                "Model {} has no version {}",
+                name,
+                version.unwrap_or_default()
+            ))
+        })
+    }
+}
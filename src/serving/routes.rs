@@ -0,0 +1,504 @@
+use super::error::{handle_rejection, ErrorDetail, ServingError};
+use super::inference::{
+    get_batch_predictions, score_labels, select_labels, to_v1_response, token_stats, validate_text,
+};
+use super::monitoring::ServingMetrics;
+use super::pool::InferencePool;
+use super::registry::{ModelRegistry, ServedModel};
+use super::state::ModelState;
+use super::types::{
+    ApiVersion, BatchMetadata, BatchPredictRequest, BatchPredictResponse, BatchPredictResponseV1,
+    BatchPredictResult, BatchPredictResultV1, LabelScore, ModelInfo, ModelRef, ModelsResponse,
+    PredictMetadata, PredictRequest, PredictResponse, TokenStats,
+};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Instant;
+use warp::{Filter, Reply};
+
+fn with_served_model(
+    model: Arc<ServedModel>,
+) -> impl Filter<Extract = (Arc<ServedModel>,), Error = Infallible> + Clone {
+    warp::any().map(move || model.clone())
+}
+
+fn with_registry(
+    registry: Arc<ModelRegistry>,
+) -> impl Filter<Extract = (Arc<ModelRegistry>,), Error = Infallible> + Clone {
+    warp::any().map(move || registry.clone())
+}
+
+fn with_inference_pool(
+    pool: InferencePool,
+) -> impl Filter<Extract = (InferencePool,), Error = Infallible> + Clone {
+    warp::any().map(move || pool.clone())
+}
+
+fn with_metrics(
+    metrics: Arc<ServingMetrics>,
+) -> impl Filter<Extract = (Arc<ServingMetrics>,), Error = Infallible> + Clone {
+    warp::any().map(move || metrics.clone())
+}
+
+/// Compare the admin token in constant time, so that it cannot be guessed from response times.
+pub fn is_authorized(header: Option<&str>, admin_token: &str) -> bool {
+    let Some(token) = header.and_then(|header| header.strip_prefix("Bearer ")) else {
+        return false;
+    };
+
+    token.len() == admin_token.len()
+        && token
+            .bytes()
+            .zip(admin_token.bytes())
+            .fold(0u8, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+fn model_ref(model: &ServedModel) -> ModelRef {
+    ModelRef {
+        model: model.name.clone(),
+        version: model.version.clone(),
+    }
+}
+
+fn observe_labels(metrics: &ServingMetrics, model: &ServedModel, labels: &[LabelScore]) {
+    metrics.observe_predictions(
+        &model.label,
+        labels
+            .iter()
+            .filter(|label| label.above_threshold)
+            .map(|label| label.label.as_str()),
+    );
+}
+
+fn observe_tokens(metrics: &ServingMetrics, stats: &[TokenStats]) {
+    let n_tokens: usize = stats.iter().map(|stats| stats.n_tokens).sum();
+    let n_unknown_tokens: usize = stats.iter().map(|stats| stats.n_unknown_tokens).sum();
+    if n_tokens > 0 {
+        metrics.observe_unk_token_ratio(n_unknown_tokens as f64 / n_tokens as f64);
+    }
+}
+
+/// Classify a single text with the given model.
+async fn predict(
+    body: PredictRequest,
+    model: Arc<ServedModel>,
+    metrics: Arc<ServingMetrics>,
+    api_version: ApiVersion,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    let started = Instant::now();
+    body.options.validate().map_err(warp::reject::custom)?;
+
+    // Reject invalid texts here, so that they do not fail the whole batch
+    validate_text(&body.text, 0, &model.state.current().tokenizer).map_err(warp::reject::custom)?;
+
+    let (predictions, data) = model
+        .batcher
+        .submit(body.text.clone())
+        .await
+        .map_err(warp::reject::custom)?;
+
+    // Use the artifacts returned with the predictions, in case they were reloaded meanwhile
+    let tokens = token_stats(
+        &body.text,
+        &data.tokenizer,
+        &data.word_to_index,
+        &data.special_tokens,
+        data.max_seq_len,
+    );
+    observe_tokens(&metrics, &[tokens]);
+
+    let labels = score_labels(
+        &predictions,
+        &data.index_to_class,
+        &data.thresholds,
+        &body.options,
+    );
+    observe_labels(&metrics, &model, &labels);
+
+    Ok(match api_version {
+        ApiVersion::V1 => warp::reply::json(&to_v1_response(
+            &predictions,
+            &data.index_to_class,
+            &body.options,
+        )),
+        ApiVersion::V2 => warp::reply::json(&PredictResponse {
+            predictions: select_labels(labels, &body.options),
+            metadata: PredictMetadata {
+                model: model_ref(&model),
+                tokens,
+                latency_ms: started.elapsed().as_secs_f64() * 1000.,
+            },
+        }),
+    })
+}
+
+/// Classify a batch of texts with the given model, in a single forward pass.
+///
+/// The items that cannot be classified, e.g. empty texts, get an error in their result instead of
+/// failing the whole batch.
+async fn predict_batch(
+    body: BatchPredictRequest,
+    model: Arc<ServedModel>,
+    pool: InferencePool,
+    metrics: Arc<ServingMetrics>,
+    max_batch_size: usize,
+    api_version: ApiVersion,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    let started = Instant::now();
+    if body.items.len() > max_batch_size {
+        return Err(warp::reject::custom(ServingError::BatchTooLarge {
+            size: body.items.len(),
+            max_size: max_batch_size,
+        }));
+    }
+    body.options.validate().map_err(warp::reject::custom)?;
+    let options = body.options;
+
+    // The whole request is served by the artifacts loaded when it arrived
+    let data = model.state.current();
+
+    let (ids, texts): (Vec<Option<String>>, Vec<String>) = body
+        .items
+        .into_iter()
+        .map(|item| (item.id, item.text))
+        .unzip();
+    let tokens: Vec<TokenStats> = texts
+        .iter()
+        .map(|text| {
+            token_stats(
+                text,
+                &data.tokenizer,
+                &data.word_to_index,
+                &data.special_tokens,
+                data.max_seq_len,
+            )
+        })
+        .collect();
+    observe_tokens(&metrics, &tokens);
+
+    let errors: Vec<Option<ServingError>> = texts
+        .iter()
+        .enumerate()
+        .map(|(index, text)| validate_text(text, index, &data.tokenizer).err())
+        .collect();
+    let valid_texts: Vec<String> = texts
+        .into_iter()
+        .zip(&errors)
+        .filter(|(_, error)| error.is_none())
+        .map(|(text, _)| text)
+        .collect();
+
+    let mut valid_scores = pool
+        .run({
+            let data = data.clone();
+            let metrics = metrics.clone();
+            move || {
+                get_batch_predictions(
+                    &valid_texts,
+                    &data.tokenizer,
+                    &data.word_to_index,
+                    &data.special_tokens,
+                    data.model.as_ref(),
+                    data.max_seq_len,
+                    &metrics,
+                )
+            }
+        })
+        .await
+        .map_err(warp::reject::custom)?
+        .into_iter();
+
+    // Put the scores back in the order of the items, the invalid ones getting none
+    let scores: Vec<Option<Vec<f32>>> = errors
+        .iter()
+        .map(|error| match error {
+            Some(_) => None,
+            None => Some(valid_scores.next().expect("one prediction per valid text")),
+        })
+        .collect();
+    let labels: Vec<Vec<LabelScore>> = scores
+        .iter()
+        .map(|scores| {
+            scores
+                .as_ref()
+                .map(|scores| {
+                    score_labels(scores, &data.index_to_class, &data.thresholds, &options)
+                })
+                .unwrap_or_default()
+        })
+        .collect();
+    let errors: Vec<Option<ErrorDetail>> = errors
+        .iter()
+        .map(|error| error.as_ref().map(ErrorDetail::from))
+        .collect();
+
+    for labels in &labels {
+        observe_labels(&metrics, &model, labels);
+    }
+
+    Ok(match api_version {
+        ApiVersion::V1 => {
+            let results = ids
+                .into_iter()
+                .zip(scores)
+                .zip(errors)
+                .map(|((id, scores), error)| {
+                    let response = scores
+                        .map(|scores| to_v1_response(&scores, &data.index_to_class, &options))
+                        .unwrap_or_default();
+                    BatchPredictResultV1 {
+                        id,
+                        predictions: response.predictions,
+                        scores: response.scores,
+                        error,
+                    }
+                })
+                .collect();
+            warp::reply::json(&BatchPredictResponseV1 { results })
+        }
+        ApiVersion::V2 => {
+            let results = ids
+                .into_iter()
+                .zip(labels)
+                .zip(tokens)
+                .zip(errors)
+                .map(|(((id, labels), tokens), error)| BatchPredictResult {
+                    id,
+                    predictions: select_labels(labels, &options),
+                    tokens,
+                    error,
+                })
+                .collect();
+            warp::reply::json(&BatchPredictResponse {
+                results,
+                metadata: BatchMetadata {
+                    model: model_ref(&model),
+                    latency_ms: started.elapsed().as_secs_f64() * 1000.,
+                },
+            })
+        }
+    })
+}
+
+/// Every served model, the default one first.
+fn served_models(
+    default_model: &Arc<ServedModel>,
+    registry: &ModelRegistry,
+) -> Vec<Arc<ServedModel>> {
+    std::iter::once(default_model)
+        .chain(registry.iter().map(|(_, _, _, model)| model))
+        .cloned()
+        .collect()
+}
+
+/// The states of every served model, the default one first, e.g. to reload or watch them.
+pub fn model_states(
+    default_model: &Arc<ServedModel>,
+    registry: &ModelRegistry,
+) -> Vec<Arc<ModelState>> {
+    served_models(default_model, registry)
+        .into_iter()
+        .map(|model| model.state.clone())
+        .collect()
+}
+
+/// Reload every model, even when some fail, so that one broken training run does not keep the
+/// other models on their old artifacts.
+///
+/// # Errors
+///
+/// This function returns `ServingError::Reload` listing every model that failed to reload.
+async fn reload_models(models: &[Arc<ServedModel>]) -> Result<(), ServingError> {
+    let mut failures = Vec::new();
+    for model in models {
+        if let Err(error) = model.state.reload().await {
+            log::error!("Failed to reload model {}: {}", model.label, error);
+            failures.push(format!("{}: {}", model.label, error));
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(ServingError::Reload(format!(
+            "Failed to reload {} of {} models. {}",
+            failures.len(),
+            models.len(),
+            failures.join("; ")
+        )))
+    }
+}
+
+/// All the routes of the server, answering errors with a JSON body and recording the metrics of
+/// every request.
+///
+/// # Arguments
+///
+/// * `default_model` - The model served by `/predict` and `/predict/batch`.
+/// * `registry` - The models served under `/models`.
+/// * `pool` - The pool running the batch predictions.
+/// * `metrics` - The metrics exposed on `/metrics`.
+/// * `admin_token` - The token required by `/admin/reload`, which is disabled when not set.
+/// * `max_batch_size` - The maximum number of texts of a batch prediction.
+pub fn routes(
+    default_model: Arc<ServedModel>,
+    registry: Arc<ModelRegistry>,
+    pool: InferencePool,
+    metrics: Arc<ServingMetrics>,
+    admin_token: Option<String>,
+    max_batch_size: usize,
+) -> impl Filter<Extract = (impl Reply,), Error = Infallible> + Clone {
+    let health_check_route = warp::get()
+        .and(warp::path("hc"))
+        .map(|| warp::reply::json(&serde_json::json!({"status": "healthy"})));
+
+    let metrics_route = warp::get()
+        .and(warp::path("metrics"))
+        .and(warp::path::end())
+        .and(with_metrics(metrics.clone()))
+        .and_then(|metrics: Arc<ServingMetrics>| async move {
+            let body = metrics.render().map_err(|error| {
+                warp::reject::custom(ServingError::Unavailable(format!(
+                    "Failed to render the metrics: {}",
+                    error
+                )))
+            })?;
+            Ok::<_, warp::Rejection>(warp::reply::with_header(
+                body,
+                "Content-Type",
+                "text/plain; version=0.0.4",
+            ))
+        });
+
+    let models = Arc::new(served_models(&default_model, &registry));
+
+    let reload_route = warp::post()
+        .and(warp::path!("admin" / "reload"))
+        .and(warp::header::optional::<String>("authorization"))
+        .and_then(move |authorization: Option<String>| {
+            let admin_token = admin_token.clone();
+            let models = models.clone();
+            async move {
+                // Without a token, nobody can reload. A not found rejection would be reported
+                // as a 405 by warp, since other routes match the path with another method.
+                let admin_token = admin_token.ok_or_else(|| {
+                    warp::reject::custom(ServingError::Unauthorized(
+                        "The reload endpoint is disabled, no admin token is set".to_string(),
+                    ))
+                })?;
+                if !is_authorized(authorization.as_deref(), &admin_token) {
+                    return Err(warp::reject::custom(ServingError::Unauthorized(
+                        "A valid admin token is required".to_string(),
+                    )));
+                }
+
+                reload_models(&models).await.map_err(warp::reject::custom)?;
+                Ok(warp::reply::json(
+                    &serde_json::json!({"status": "reloaded"}),
+                ))
+            }
+        });
+
+    let list_models_route = warp::get()
+        .and(warp::path("models"))
+        .and(warp::path::end())
+        .and(with_registry(registry.clone()))
+        .map(|registry: Arc<ModelRegistry>| {
+            let models = registry
+                .iter()
+                .map(|(name, version, latest, model)| ModelInfo {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    latest,
+                    bundle: model.state.current().bundle.clone(),
+                })
+                .collect();
+            warp::reply::json(&ModelsResponse { models })
+        });
+
+    let predict_route = warp::post()
+        .and(warp::path("predict"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(with_served_model(default_model.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(|body, model, metrics| predict(body, model, metrics, ApiVersion::V2));
+
+    let predict_v1_route = warp::post()
+        .and(warp::path!("v1" / "predict"))
+        .and(warp::body::json())
+        .and(with_served_model(default_model.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(|body, model, metrics| predict(body, model, metrics, ApiVersion::V1));
+
+    let predict_latest_route = warp::post()
+        .and(warp::path!("models" / String / "predict"))
+        .and(warp::body::json())
+        .and(with_registry(registry.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(
+            |name: String,
+             body: PredictRequest,
+             registry: Arc<ModelRegistry>,
+             metrics: Arc<ServingMetrics>| async move {
+                let model = registry.get(&name, None).map_err(warp::reject::custom)?;
+                predict(body, model, metrics, ApiVersion::V2).await
+            },
+        );
+
+    let predict_version_route = warp::post()
+        .and(warp::path!(
+            "models" / String / "versions" / String / "predict"
+        ))
+        .and(warp::body::json())
+        .and(with_registry(registry))
+        .and(with_metrics(metrics.clone()))
+        .and_then(
+            |name: String,
+             version: String,
+             body: PredictRequest,
+             registry: Arc<ModelRegistry>,
+             metrics: Arc<ServingMetrics>| async move {
+                let model = registry
+                    .get(&name, Some(&version))
+                    .map_err(warp::reject::custom)?;
+                predict(body, model, metrics, ApiVersion::V2).await
+            },
+        );
+
+    let predict_batch_route = warp::post()
+        .and(warp::path!("predict" / "batch"))
+        .and(warp::body::json())
+        .and(with_served_model(default_model.clone()))
+        .and(with_inference_pool(pool.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(move |body, model, pool, metrics| {
+            predict_batch(body, model, pool, metrics, max_batch_size, ApiVersion::V2)
+        });
+
+    let predict_batch_v1_route = warp::post()
+        .and(warp::path!("v1" / "predict" / "batch"))
+        .and(warp::body::json())
+        .and(with_served_model(default_model))
+        .and(with_inference_pool(pool))
+        .and(with_metrics(metrics.clone()))
+        .and_then(move |body, model, pool, metrics| {
+            predict_batch(body, model, pool, metrics, max_batch_size, ApiVersion::V1)
+        });
+
+    health_check_route
+        .or(metrics_route)
+        .or(reload_route)
+        .or(list_models_route)
+        .or(predict_latest_route)
+        .or(predict_version_route)
+        .or(predict_route)
+        .or(predict_batch_route)
+        .or(predict_v1_route)
+        .or(predict_batch_v1_route)
+        .recover(handle_rejection)
+        .with(warp::log::custom(move |info| {
+            metrics.observe_request(info.path(), info.status().as_u16(), info.elapsed())
+        }))
+}
@@ -1,93 +1,77 @@
-use candle_core::{DType, Device};
-use common::{self, PREDICTION_THRESHOLD};
-mod inference;
-mod types;
-
-use candle_nn::{VarBuilder, VarMap};
-use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
-use common::{
-    create_vocabulary_to_index_mapping, load_index_to_class_mapping, load_vocabulary,
-    HeadlineClassifierModel, ModelConfig, INDEX_TO_CLASS_PATH, MODEL_PATH, VOCAB_PATH,
-};
-use inference::{get_predictions, map_to_class_names_with_scores};
-use types::{PredictRequest, PredictResponse};
-use warp::Filter;
-
-fn with_shared_data(
-    shared_data: SharedData,
-) -> impl Filter<Extract = (SharedData,), Error = std::convert::Infallible> + Clone {
-    warp::any().map(move || shared_data.clone())
-}
+use clap::Parser;
+use common::monitoring::ServingMetrics;
+use common::pool::InferencePool;
+use common::registry::{ModelRegistry, ServedModel};
+use common::routes::{model_states, routes};
+use common::serving_config::Args;
 
-#[derive(Clone)]
-struct SharedData {
-    word_to_index: Arc<HashMap<String, u32>>,
-    index_to_class: Arc<HashMap<u32, String>>,
-    model: Arc<HeadlineClassifierModel>,
-}
+/// Name of the model loaded from `--model-dir`, served by `/predict` and `/predict/batch`.
+const DEFAULT_MODEL_NAME: &str = "default";
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    env_logger::init();
-
-    // Load the vocabulary and the mappings
-    let vocabulary = load_vocabulary(VOCAB_PATH)?;
-
-    let word_to_index = Arc::new(create_vocabulary_to_index_mapping(&vocabulary));
-    let index_to_class = load_index_to_class_mapping(INDEX_TO_CLASS_PATH)?;
-
-    let index_to_class = Arc::new(index_to_class);
-    let model_config = Arc::new(ModelConfig::default());
-
-    // Load the model from MODEL_PATH via a VarMap
-    let mut varmap = VarMap::new();
-    varmap.load(MODEL_PATH)?;
-
-    let device = Device::cuda_if_available(0)?;
-
-    let vs = VarBuilder::from_varmap(&varmap, DType::F32, &device);
-
-    let model = Arc::new(HeadlineClassifierModel::new(&vs, &model_config)?);
-
-    // Build the shared data
-    let shared_data = SharedData {
-        word_to_index: Arc::clone(&word_to_index),
-        index_to_class: Arc::clone(&index_to_class),
-        model: Arc::clone(&model),
-    };
-
-    let health_check_route = warp::get()
-        .and(warp::path("hc"))
-        .map(|| warp::reply::json(&serde_json::json!({"status": "healthy"})));
-
-    let predict_route = warp::post()
-        .and(warp::path("predict"))
-        .and(warp::body::json())
-        .and(with_shared_data(shared_data))
-        .and_then(|body: PredictRequest, data: SharedData| async move {
-            match get_predictions(&body.text, &data.word_to_index, &data.model) {
-                Ok(predictions) => {
-                    let predicted_categories = map_to_class_names_with_scores(
-                        predictions,
-                        &data.index_to_class,
-                        PREDICTION_THRESHOLD,
-                    );
-                    let response = PredictResponse {
-                        predictions: predicted_categories,
-                    };
-                    Ok::<_, warp::Rejection>(warp::reply::json(&response))
-                }
-                Err(error) => Ok(warp::reply::json(
-                    &serde_json::json!({"error": error.to_string()}),
-                )),
-            }
-        });
-
-    let routes = health_check_route.or(predict_route);
-
-    warp::serve(routes).run(([127, 0, 0, 1], 3030)).await;
+    let args = Args::parse();
+
+    match &args.log_level {
+        Some(filters) => env_logger::Builder::new().parse_filters(filters).init(),
+        None => env_logger::init(),
+    }
+
+    let metrics = Arc::new(ServingMetrics::new()?);
+
+    let pool = InferencePool::new(args.max_concurrency(), args.queue_depth)?;
+    log::info!(
+        "Running at most {} predictions at a time, with {} more queued",
+        args.max_concurrency(),
+        args.queue_depth
+    );
+    log::info!(
+        "Batching up to {} predictions, waiting at most {}ms",
+        args.micro_batch_size,
+        args.micro_batch_wait_ms
+    );
+
+    let batching = args.batching_config();
+    let default_model = Arc::new(ServedModel::load(
+        DEFAULT_MODEL_NAME.to_string(),
+        None,
+        args.artifact_paths(),
+        &pool,
+        &metrics,
+        batching,
+    )?);
+
+    let registry = Arc::new(match &args.models_dir {
+        Some(directory) => ModelRegistry::load(directory, |name, version, paths| {
+            ServedModel::load(name, version, paths, &pool, &metrics, batching)
+        })?,
+        None => ModelRegistry::default(),
+    });
+
+    if let Some(interval) = args.reload_interval_secs {
+        log::info!(
+            "Checking the model artifacts for changes every {}s",
+            interval
+        );
+        for state in model_states(&default_model, &registry) {
+            tokio::spawn(state.watch(Duration::from_secs(interval)));
+        }
+    }
+
+    let routes = routes(
+        default_model,
+        registry,
+        pool,
+        metrics,
+        args.admin_token.clone(),
+        args.max_batch_size,
+    );
+
+    log::info!("Listening on {}:{}", args.host, args.port);
+    warp::serve(routes).run((args.host, args.port)).await;
 
     Ok(())
 }
@@ -0,0 +1,115 @@
+use super::error::ServingError;
+use super::loader::{load_shared_data, SharedData};
+use super::monitoring::ServingMetrics;
+use crate::ArtifactPaths;
+use arc_swap::ArcSwap;
+use std::{fs, sync::Arc, sync::Mutex, time::Duration, time::SystemTime};
+
+/// The modification time of the model config, used to detect a new training run.
+///
+/// Training writes the model config after every other artifact, so the artifacts are only
+/// reloaded once a run is complete, rather than mixing the files of two runs.
+fn artifacts_modified_at(paths: &ArtifactPaths) -> Option<SystemTime> {
+    fs::metadata(&paths.model_config)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// Holds the artifacts currently served, and swaps them for new ones without a restart.
+///
+/// Handlers take a snapshot with `current` when a request starts, so a request in flight during a
+/// reload completes against the artifacts it started with.
+pub struct ModelState {
+    current: ArcSwap<SharedData>,
+    paths: ArtifactPaths,
+    metrics: Arc<ServingMetrics>,
+    /// Serializes reloads, so that an older load does not replace a newer one.
+    reloading: tokio::sync::Mutex<()>,
+    /// The modification time of the artifacts last loaded.
+    loaded_at: Mutex<Option<SystemTime>>,
+}
+
+impl ModelState {
+    /// Load the artifacts found at `paths`.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the artifacts cannot be loaded.
+    pub fn load(paths: ArtifactPaths, metrics: Arc<ServingMetrics>) -> anyhow::Result<Self> {
+        let loaded_at = artifacts_modified_at(&paths);
+        let data = load_shared_data(&paths)?;
+
+        Ok(Self {
+            current: ArcSwap::from_pointee(data),
+            paths,
+            metrics,
+            reloading: tokio::sync::Mutex::new(()),
+            loaded_at: Mutex::new(loaded_at),
+        })
+    }
+
+    pub fn current(&self) -> Arc<SharedData> {
+        self.current.load_full()
+    }
+
+    /// Load and validate the artifacts on a blocking thread, then swap them in. On failure, the
+    /// artifacts currently served are kept.
+    ///
+    /// # Errors
+    ///
+    /// This function returns `ServingError::Reload` if the new artifacts cannot be loaded.
+    pub async fn reload(&self) -> Result<(), ServingError> {
+        let _reloading = self.reloading.lock().await;
+
+        // Record the modification time first, so that a broken run is not retried until it changes
+        *self.loaded_at.lock().unwrap() = artifacts_modified_at(&self.paths);
+
+        let paths = self.paths.clone();
+        let result = tokio::task::spawn_blocking(move || load_shared_data(&paths))
+            .await
+            .map_err(|error| ServingError::Reload(format!("The reload task failed: {}", error)))
+            .and_then(|result| {
+                result.map_err(|error| {
+                    ServingError::Reload(format!("Failed to load the artifacts: {:#}", error))
+                })
+            });
+        self.metrics.observe_reload(result.is_ok());
+
+        let data = result?;
+        self.current.store(Arc::new(data));
+        log::info!("Reloaded the model artifacts from {:?}", self.paths);
+        Ok(())
+    }
+
+    /// Reload the artifacts if a training run completed since they were last loaded.
+    ///
+    /// # Errors
+    ///
+    /// This function returns `ServingError::Reload` if the new artifacts cannot be loaded.
+    ///
+    /// # Returns
+    ///
+    /// Whether the artifacts were reloaded.
+    pub async fn reload_if_changed(&self) -> Result<bool, ServingError> {
+        let modified_at = artifacts_modified_at(&self.paths);
+        if modified_at.is_none() || modified_at == *self.loaded_at.lock().unwrap() {
+            return Ok(false);
+        }
+
+        log::info!("The model artifacts changed on disk, reloading them");
+        self.reload().await?;
+        Ok(true)
+    }
+
+    /// Check the artifacts every `interval` and reload them when they change on disk.
+    pub async fn watch(self: Arc<Self>, interval: Duration) {
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            ticks.tick().await;
+
+            if let Err(error) = self.reload_if_changed().await {
+                log::error!("{}", error);
+            }
+        }
+    }
+}
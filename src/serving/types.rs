@@ -1,12 +1,181 @@
+use super::error::{ErrorDetail, ServingError};
+use crate::ModelBundle;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// The shape of the prediction responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    /// The original shape, served under `/v1`: one single-entry map per class.
+    V1,
+    /// The current shape: sorted label objects, with metadata about the model and the text.
+    V2,
+}
+
+/// Controls which classes are returned for a text.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PredictionOptions {
+    /// Minimum score of the returned classes. Defaults to the per-class thresholds of the model.
+    pub threshold: Option<f32>,
+    /// When set, the `top_k` highest-scoring classes are returned regardless of the threshold.
+    pub top_k: Option<usize>,
+    /// Also return the scores of every class.
+    #[serde(default)]
+    pub include_all_scores: bool,
+}
+
+impl PredictionOptions {
+    /// # Errors
+    ///
+    /// This function returns `ServingError::Validation` if the threshold is not within [0, 1] or `top_k` is 0.
+    pub fn validate(&self) -> Result<(), ServingError> {
+        if let Some(threshold) = self.threshold.filter(|t| !(0.0..=1.0).contains(t)) {
+            return Err(ServingError::Validation(format!(
+                "The threshold must be between 0 and 1, got {}",
+                threshold
+            )));
+        }
+        if self.top_k == Some(0) {
+            return Err(ServingError::Validation(
+                "top_k must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Deserialize)]
 pub struct PredictRequest {
     pub text: String,
+    #[serde(flatten)]
+    pub options: PredictionOptions,
+}
+
+#[derive(Deserialize)]
+pub struct BatchPredictItem {
+    /// Optional client-supplied identifier, echoed back in the response.
+    pub id: Option<String>,
+    pub text: String,
+}
+
+#[derive(Deserialize)]
+pub struct BatchPredictRequest {
+    pub items: Vec<BatchPredictItem>,
+    /// Applied to every item.
+    #[serde(flatten)]
+    pub options: PredictionOptions,
+}
+
+/// A class and its score.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LabelScore {
+    pub label: String,
+    pub score: f32,
+    /// Whether the score is at or above the threshold of the class.
+    pub above_threshold: bool,
+}
+
+/// How a text was seen by the model.
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenStats {
+    /// Number of tokens fed to the model, without the special tokens.
+    pub n_tokens: usize,
+    /// Number of these tokens missing from the vocabulary.
+    pub n_unknown_tokens: usize,
+    /// Whether tokens were dropped to fit the sequence length of the model.
+    pub truncated: bool,
+}
+
+/// The model that served a request.
+#[derive(Serialize, Debug, Clone)]
+pub struct ModelRef {
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PredictMetadata {
+    #[serde(flatten)]
+    pub model: ModelRef,
+    #[serde(flatten)]
+    pub tokens: TokenStats,
+    /// Time spent serving the request, in milliseconds.
+    pub latency_ms: f64,
 }
 
 #[derive(Serialize)]
 pub struct PredictResponse {
+    /// The selected classes, from the highest score to the lowest.
+    pub predictions: Vec<LabelScore>,
+    pub metadata: PredictMetadata,
+}
+
+#[derive(Serialize)]
+pub struct BatchPredictResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub predictions: Vec<LabelScore>,
+    #[serde(flatten)]
+    pub tokens: TokenStats,
+    /// Why the item could not be classified, in which case it has no predictions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorDetail>,
+}
+
+#[derive(Serialize)]
+pub struct BatchMetadata {
+    #[serde(flatten)]
+    pub model: ModelRef,
+    /// Time spent serving the request, in milliseconds.
+    pub latency_ms: f64,
+}
+
+#[derive(Serialize)]
+pub struct BatchPredictResponse {
+    /// The results, in the order of the request items.
+    pub results: Vec<BatchPredictResult>,
+    pub metadata: BatchMetadata,
+}
+
+#[derive(Serialize, Default)]
+pub struct PredictResponseV1 {
+    /// The selected classes with their scores, in the order of the class indices.
     pub predictions: Vec<HashMap<String, f32>>,
+    /// The scores of every class, when `include_all_scores` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scores: Option<HashMap<String, f32>>,
+}
+
+#[derive(Serialize)]
+pub struct BatchPredictResultV1 {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub predictions: Vec<HashMap<String, f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scores: Option<HashMap<String, f32>>,
+    /// Why the item could not be classified, in which case it has no predictions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorDetail>,
+}
+
+#[derive(Serialize)]
+pub struct BatchPredictResponseV1 {
+    /// The results, in the order of the request items.
+    pub results: Vec<BatchPredictResultV1>,
+}
+
+#[derive(Serialize)]
+pub struct ModelInfo {
+    pub name: String,
+    pub version: String,
+    /// Whether this version is the one served by `/models/{name}/predict`.
+    pub latest: bool,
+    #[serde(flatten)]
+    pub bundle: ModelBundle,
+}
+
+#[derive(Serialize)]
+pub struct ModelsResponse {
+    pub models: Vec<ModelInfo>,
 }
@@ -1,7 +1,32 @@
+use crate::embeddings::EmbeddingsFormat;
+use crate::{
+    Architecture, ModelConfig, Pooling, TokenizerConfig, INDEX_TO_CLASS_PATH, MODEL_CONFIG_PATH,
+    MODEL_PATH, VOCAB_PATH,
+};
+use anyhow::{Context, Error};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+/// Name of the file the effective configuration is written to, next to the model weights.
+pub const EFFECTIVE_CONFIG_FILE_NAME: &str = "train_config.toml";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct TrainConfig {
     pub n_epochs: u32,
     pub learning_rate: f64,
     pub early_stop_patience: u8,
+    pub batch_size: usize,
+    pub shuffle: bool,
+    pub drop_last: bool,
+    pub seed: u64,
+    /// Share of the training data held out to select the best epoch and calibrate the thresholds.
+    pub validation_fraction: f32,
+    /// Search per-class decision thresholds on the validation data once training is done.
+    pub calibrate_thresholds: bool,
+    /// The beta of the F-beta score maximized by the threshold search. 1 maximizes the F1 score.
+    pub calibration_beta: f32,
 }
 
 impl Default for TrainConfig {
@@ -10,6 +35,286 @@ impl Default for TrainConfig {
             n_epochs: 100,
             learning_rate: 0.001,
             early_stop_patience: 20,
+            batch_size: 32,
+            shuffle: true,
+            drop_last: false,
+            seed: 42,
+            validation_fraction: 0.1,
+            calibrate_thresholds: true,
+            calibration_beta: 1.0,
         }
     }
 }
+
+/// Settings of the `VocabularyBuilder`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct VocabularyConfig {
+    pub min_freq: usize,
+    pub max_size: Option<usize>,
+    pub stop_words: Vec<String>,
+}
+
+impl Default for VocabularyConfig {
+    fn default() -> Self {
+        Self {
+            min_freq: 1,
+            max_size: None,
+            stop_words: Vec::new(),
+        }
+    }
+}
+
+/// Initialization of the embedding table from pretrained word vectors.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct EmbeddingsConfig {
+    /// File of pretrained word vectors. The embedding table is randomly initialized when not set.
+    pub path: Option<String>,
+    /// Format of the file, guessed from its extension when not set.
+    pub format: Option<EmbeddingsFormat>,
+    /// Keep the pretrained vectors fixed during training instead of fine-tuning them. The rows of
+    /// the special tokens and of the words without a vector are still trained.
+    pub freeze: bool,
+}
+
+impl EmbeddingsConfig {
+    /// The format of the file at `path`, given or guessed from its extension.
+    pub fn format(&self, path: &str) -> EmbeddingsFormat {
+        self.format
+            .unwrap_or_else(|| EmbeddingsFormat::from_path(path))
+    }
+}
+
+/// Input and output paths of a training run.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PathsConfig {
+    pub train_data: String,
+    pub test_data: String,
+    pub model: String,
+    pub model_config: String,
+    pub vocabulary: String,
+    pub index_to_class: String,
+}
+
+impl Default for PathsConfig {
+    fn default() -> Self {
+        Self {
+            train_data: "data/train.csv".to_string(),
+            test_data: "data/test.csv".to_string(),
+            model: MODEL_PATH.to_string(),
+            model_config: MODEL_CONFIG_PATH.to_string(),
+            vocabulary: VOCAB_PATH.to_string(),
+            index_to_class: INDEX_TO_CLASS_PATH.to_string(),
+        }
+    }
+}
+
+/// The complete configuration of a training run, as read from a TOML file.
+///
+/// Every section and field is optional and falls back to its default value. The `vocab_size` and
+/// `n_classes` of the model are always derived from the training data, and the `embedding_size`
+/// from the pretrained embeddings when they are set.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    pub training: TrainConfig,
+    pub model: ModelConfig,
+    pub tokenizer: TokenizerConfig,
+    pub vocabulary: VocabularyConfig,
+    pub embeddings: EmbeddingsConfig,
+    pub paths: PathsConfig,
+}
+
+/// Train a headline classifier.
+///
+/// Settings are read from the optional TOML config file, then overridden by the command-line options.
+#[derive(Parser, Debug, Default)]
+#[command(version)]
+pub struct Args {
+    /// Path to a TOML config file.
+    #[arg(long)]
+    pub config: Option<String>,
+
+    #[arg(long)]
+    pub n_epochs: Option<u32>,
+    #[arg(long)]
+    pub learning_rate: Option<f64>,
+    #[arg(long)]
+    pub early_stop_patience: Option<u8>,
+    #[arg(long)]
+    pub batch_size: Option<usize>,
+    #[arg(long)]
+    pub shuffle: Option<bool>,
+    #[arg(long)]
+    pub drop_last: Option<bool>,
+    #[arg(long)]
+    pub seed: Option<u64>,
+    #[arg(long)]
+    pub validation_fraction: Option<f32>,
+    #[arg(long)]
+    pub calibrate_thresholds: Option<bool>,
+    #[arg(long)]
+    pub calibration_beta: Option<f32>,
+
+    #[arg(long, value_enum)]
+    pub architecture: Option<Architecture>,
+    #[arg(long)]
+    pub embedding_size: Option<usize>,
+    #[arg(long)]
+    pub hidden_size: Option<usize>,
+    #[arg(long)]
+    pub max_seq_len: Option<usize>,
+    /// Comma-separated widths of the convolution kernels of the cnn architecture.
+    #[arg(long, value_delimiter = ',')]
+    pub kernel_sizes: Option<Vec<usize>>,
+    #[arg(long)]
+    pub n_filters: Option<usize>,
+    #[arg(long, value_enum)]
+    pub pooling: Option<Pooling>,
+    #[arg(long)]
+    pub n_layers: Option<usize>,
+    #[arg(long)]
+    pub n_heads: Option<usize>,
+    #[arg(long)]
+    pub dropout: Option<f32>,
+
+    #[arg(long)]
+    pub lowercase: Option<bool>,
+    #[arg(long)]
+    pub strip_punctuation: Option<bool>,
+    #[arg(long)]
+    pub normalize_unicode: Option<bool>,
+
+    #[arg(long)]
+    pub min_freq: Option<usize>,
+    #[arg(long)]
+    pub max_vocab_size: Option<usize>,
+    /// Comma-separated list of words excluded from the vocabulary.
+    #[arg(long, value_delimiter = ',')]
+    pub stop_words: Option<Vec<String>>,
+
+    /// File of pretrained word vectors initializing the embedding table.
+    #[arg(long)]
+    pub pretrained_embeddings: Option<String>,
+    #[arg(long, value_enum)]
+    pub embeddings_format: Option<EmbeddingsFormat>,
+    #[arg(long)]
+    pub freeze_embeddings: Option<bool>,
+
+    #[arg(long)]
+    pub train_data: Option<String>,
+    #[arg(long)]
+    pub test_data: Option<String>,
+    #[arg(long)]
+    pub model_path: Option<String>,
+    #[arg(long)]
+    pub model_config_path: Option<String>,
+    #[arg(long)]
+    pub vocab_path: Option<String>,
+    #[arg(long)]
+    pub index_to_class_path: Option<String>,
+}
+
+fn set<T>(target: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *target = value;
+    }
+}
+
+impl Config {
+    /// Load a configuration from a TOML file.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - A string containing the path to the TOML file.
+    ///
+    /// # Errors
+    ///
+    /// This function can return an error if the file cannot be read or is not a valid configuration.
+    pub fn from_file(file_path: &str) -> Result<Self, Error> {
+        let content = fs::read_to_string(file_path)
+            .with_context(|| format!("Failed to read config file {}", file_path))?;
+        toml::from_str(&content).with_context(|| format!("Invalid config file {}", file_path))
+    }
+
+    /// Build the configuration from the config file given in the arguments, if any, and apply the
+    /// command-line overrides on top of it.
+    pub fn from_args(args: Args) -> Result<Self, Error> {
+        let mut config = match &args.config {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+
+        let training = &mut config.training;
+        set(&mut training.n_epochs, args.n_epochs);
+        set(&mut training.learning_rate, args.learning_rate);
+        set(&mut training.early_stop_patience, args.early_stop_patience);
+        set(&mut training.batch_size, args.batch_size);
+        set(&mut training.shuffle, args.shuffle);
+        set(&mut training.drop_last, args.drop_last);
+        set(&mut training.seed, args.seed);
+        set(&mut training.validation_fraction, args.validation_fraction);
+        set(
+            &mut training.calibrate_thresholds,
+            args.calibrate_thresholds,
+        );
+        set(&mut training.calibration_beta, args.calibration_beta);
+
+        let model = &mut config.model;
+        set(&mut model.architecture, args.architecture);
+        set(&mut model.embedding_size, args.embedding_size);
+        set(&mut model.hidden_size, args.hidden_size);
+        set(&mut model.max_seq_len, args.max_seq_len);
+        set(&mut model.kernel_sizes, args.kernel_sizes);
+        set(&mut model.n_filters, args.n_filters);
+        set(&mut model.pooling, args.pooling);
+        set(&mut model.n_layers, args.n_layers);
+        set(&mut model.n_heads, args.n_heads);
+        set(&mut model.dropout, args.dropout);
+
+        let tokenizer = &mut config.tokenizer;
+        set(&mut tokenizer.lowercase, args.lowercase);
+        set(&mut tokenizer.strip_punctuation, args.strip_punctuation);
+        set(&mut tokenizer.normalize_unicode, args.normalize_unicode);
+
+        let vocabulary = &mut config.vocabulary;
+        set(&mut vocabulary.min_freq, args.min_freq);
+        set(&mut vocabulary.max_size, args.max_vocab_size.map(Some));
+        set(&mut vocabulary.stop_words, args.stop_words);
+
+        let embeddings = &mut config.embeddings;
+        set(&mut embeddings.path, args.pretrained_embeddings.map(Some));
+        set(&mut embeddings.format, args.embeddings_format.map(Some));
+        set(&mut embeddings.freeze, args.freeze_embeddings);
+
+        let paths = &mut config.paths;
+        set(&mut paths.train_data, args.train_data);
+        set(&mut paths.test_data, args.test_data);
+        set(&mut paths.model, args.model_path);
+        set(&mut paths.model_config, args.model_config_path);
+        set(&mut paths.vocabulary, args.vocab_path);
+        set(&mut paths.index_to_class, args.index_to_class_path);
+
+        Ok(config)
+    }
+
+    /// Write the configuration as TOML into the directory holding the model weights.
+    ///
+    /// # Errors
+    ///
+    /// This function can return an error if the configuration cannot be serialized or the file cannot be written.
+    ///
+    /// # Returns
+    ///
+    /// The path of the written file.
+    pub fn store_effective(&self) -> Result<String, Error> {
+        let directory = Path::new(&self.paths.model)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+        let path = directory.join(EFFECTIVE_CONFIG_FILE_NAME);
+        fs::write(&path, toml::to_string_pretty(self)?)?;
+        Ok(path.to_string_lossy().into_owned())
+    }
+}
@@ -1,14 +1,142 @@
 use anyhow::Error;
 use candle_core::Tensor;
 use polars::io::{csv::CsvReader, SerReader};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
 #[derive(Clone)]
 pub struct Dataset {
     pub train_data: Tensor,
     pub train_labels: Tensor,
+    /// Held out of the training data, to select the best epoch and calibrate the thresholds.
+    pub validation_data: Tensor,
+    pub validation_labels: Tensor,
+    /// Only used to report the scores of the trained model.
     pub test_data: Tensor,
     pub test_labels: Tensor,
 }
 
+impl Dataset {
+    /// Check that the tensors are consistent with each other and with the model dimensions.
+    ///
+    /// # Arguments
+    ///
+    /// * `vocab_size` - The size of the embedding table. Every token index must be lower than this value.
+    /// * `n_classes` - The number of model outputs, which must match the number of label columns.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error describing the first inconsistency found.
+    pub fn validate(&self, vocab_size: usize, n_classes: usize) -> Result<(), Error> {
+        for (split, data, labels) in [
+            ("train", &self.train_data, &self.train_labels),
+            ("validation", &self.validation_data, &self.validation_labels),
+            ("test", &self.test_data, &self.test_labels),
+        ] {
+            let (n_samples, _) = data.dims2()?;
+            let (n_label_samples, n_label_classes) = labels.dims2()?;
+
+            if n_samples != n_label_samples {
+                anyhow::bail!(
+                    "The {} split has {} samples but {} labels",
+                    split,
+                    n_samples,
+                    n_label_samples
+                );
+            }
+
+            if n_label_classes != n_classes {
+                anyhow::bail!(
+                    "The {} labels have {} classes but the model has {} outputs",
+                    split,
+                    n_label_classes,
+                    n_classes
+                );
+            }
+
+            if data.elem_count() > 0 {
+                let max_index = data.flatten_all()?.max(0)?.to_scalar::<u32>()? as usize;
+                if max_index >= vocab_size {
+                    anyhow::bail!(
+                        "The {} data contains the token index {} but the embedding table only has {} entries",
+                        split,
+                        max_index,
+                        vocab_size
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Encoded samples of shape `(n_samples, max_seq_len)` and their labels of shape `(n_samples, n_classes)`.
+pub type Samples = (Tensor, Tensor);
+
+/// Randomly split samples into a training and a validation part.
+///
+/// # Arguments
+///
+/// * `data` - A tensor of shape `(n_samples, max_seq_len)` containing the encoded samples.
+/// * `labels` - A tensor of shape `(n_samples, n_classes)` containing the multi-hot labels.
+/// * `validation_fraction` - The share of the samples held out for validation, within ]0, 1[.
+/// * `seed` - The seed of the RNG used to draw the validation samples.
+///
+/// # Errors
+///
+/// This function returns an error if the fraction is not within ]0, 1[, if there are less than two
+/// samples or if the number of samples in `data` and `labels` differ.
+///
+/// # Returns
+///
+/// The `(data, labels)` of the training part, then of the validation part, which holds at least
+/// one sample and leaves at least one for training.
+pub fn split_validation(
+    data: &Tensor,
+    labels: &Tensor,
+    validation_fraction: f32,
+    seed: u64,
+) -> Result<(Samples, Samples), Error> {
+    if !(validation_fraction > 0. && validation_fraction < 1.) {
+        anyhow::bail!(
+            "The validation fraction must be between 0 and 1, got {}",
+            validation_fraction
+        );
+    }
+
+    let n_samples = data.dim(0)?;
+    if labels.dim(0)? != n_samples {
+        anyhow::bail!(
+            "Data has {} samples but labels have {} samples",
+            n_samples,
+            labels.dim(0)?
+        );
+    }
+    if n_samples < 2 {
+        anyhow::bail!(
+            "At least 2 samples are needed to hold out a validation split, got {}",
+            n_samples
+        );
+    }
+
+    let n_validation =
+        ((n_samples as f32 * validation_fraction).round() as usize).clamp(1, n_samples - 1);
+
+    let mut order: Vec<u32> = (0..n_samples as u32).collect();
+    order.shuffle(&mut StdRng::seed_from_u64(seed));
+    let (validation_order, train_order) = order.split_at(n_validation);
+
+    let select = |order: &[u32]| -> Result<Samples, Error> {
+        let indices = Tensor::from_slice(order, order.len(), data.device())?;
+        Ok((
+            data.index_select(&indices, 0)?,
+            labels.index_select(&indices, 0)?,
+        ))
+    };
+
+    Ok((select(train_order)?, select(validation_order)?))
+}
+
 /// Convert a Polars Series into a vector of strings.
 ///
 /// # Arguments
@@ -55,9 +183,136 @@ pub fn read_data(path: &str) -> Result<(Vec<String>, Vec<String>), Error> {
 
     let labels_series = df.column("labels")?;
 
-    let data = convert_series_to_string_vector(&data_series)?;
+    let data = convert_series_to_string_vector(data_series)?;
 
-    let labels = convert_series_to_string_vector(&labels_series)?;
+    let labels = convert_series_to_string_vector(labels_series)?;
 
     Ok((data, labels))
 }
+
+/// Iterates over a data tensor and its labels in mini-batches.
+///
+/// The samples are laid out along the first dimension of both `data` and `labels`. When shuffling is enabled, the samples are permuted at the start of every epoch using
+/// a seeded RNG, so that a training run is reproducible.
+pub struct DataLoader {
+    data: Tensor,
+    labels: Tensor,
+    batch_size: usize,
+    shuffle: bool,
+    drop_last: bool,
+    rng: StdRng,
+}
+
+impl DataLoader {
+    /// Create a new data loader over `data` and `labels`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - A tensor of shape `(n_samples, max_seq_len)` containing the encoded samples.
+    /// * `labels` - A tensor of shape `(n_samples, n_classes)` containing the multi-hot labels.
+    /// * `batch_size` - The maximum number of samples in a batch.
+    /// * `shuffle` - Whether to shuffle the samples at the start of every epoch.
+    /// * `drop_last` - Whether to skip the last batch when it has less than `batch_size` samples.
+    /// * `seed` - The seed of the RNG used for shuffling.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the batch size is zero or if the number of samples in `data` and `labels` differ.
+    pub fn new(
+        data: Tensor,
+        labels: Tensor,
+        batch_size: usize,
+        shuffle: bool,
+        drop_last: bool,
+        seed: u64,
+    ) -> Result<Self, Error> {
+        if batch_size == 0 {
+            anyhow::bail!("The batch size must be greater than zero");
+        }
+
+        let n_data_samples = data.dim(0)?;
+        let n_label_samples = labels.dim(0)?;
+        if n_data_samples != n_label_samples {
+            anyhow::bail!(
+                "Data has {} samples but labels have {} samples",
+                n_data_samples,
+                n_label_samples
+            );
+        }
+
+        Ok(Self {
+            data,
+            labels,
+            batch_size,
+            shuffle,
+            drop_last,
+            rng: StdRng::seed_from_u64(seed),
+        })
+    }
+
+    /// The number of samples the loader iterates over.
+    pub fn n_samples(&self) -> usize {
+        self.labels.dims()[0]
+    }
+
+    /// The number of batches produced in each epoch.
+    pub fn n_batches(&self) -> usize {
+        if self.drop_last {
+            self.n_samples() / self.batch_size
+        } else {
+            self.n_samples().div_ceil(self.batch_size)
+        }
+    }
+
+    /// Start a new epoch, returning an iterator over its `(data, labels)` batches.
+    pub fn epoch(&mut self) -> Batches<'_> {
+        let mut order: Vec<u32> = (0..self.n_samples() as u32).collect();
+        if self.shuffle {
+            order.shuffle(&mut self.rng);
+        }
+
+        let mut chunks: Vec<Vec<u32>> = order
+            .chunks(self.batch_size)
+            .map(|chunk| chunk.to_vec())
+            .collect();
+        if self.drop_last
+            && chunks
+                .last()
+                .is_some_and(|chunk| chunk.len() < self.batch_size)
+        {
+            chunks.pop();
+        }
+
+        Batches {
+            loader: self,
+            chunks: chunks.into_iter(),
+        }
+    }
+}
+
+/// The batches of a single epoch, produced by `DataLoader::epoch`.
+pub struct Batches<'a> {
+    loader: &'a DataLoader,
+    chunks: std::vec::IntoIter<Vec<u32>>,
+}
+
+impl Iterator for Batches<'_> {
+    type Item = Result<(Tensor, Tensor), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = self.chunks.next()?;
+
+        let batch = || -> Result<(Tensor, Tensor), Error> {
+            let indices = Tensor::from_vec(chunk.clone(), chunk.len(), self.loader.data.device())?;
+            let data = self.loader.data.index_select(&indices, 0)?;
+            let labels = self.loader.labels.index_select(&indices, 0)?;
+            Ok((data, labels))
+        };
+
+        Some(batch())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
@@ -0,0 +1,392 @@
+use anyhow::{Context, Error};
+use candle_core::{Tensor, Var};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Format of a file of pretrained word vectors.
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingsFormat {
+    /// A word and its values per line, separated by spaces, as in the GloVe, word2vec text and
+    /// fastText `.vec` files. The first line may give the number of words and the vector size.
+    Text,
+    /// The word2vec binary format: a line with the number of words and the vector size, then
+    /// every word followed by a space and its values as little-endian 32-bit floats.
+    Binary,
+}
+
+impl EmbeddingsFormat {
+    /// Guess the format from the extension of the file: `.bin` files are binary, the other files
+    /// are text.
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("bin") => Self::Binary,
+            _ => Self::Text,
+        }
+    }
+}
+
+/// How much of a vocabulary is covered by pretrained vectors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmbeddingCoverage {
+    /// The number of words of the vocabulary, without the special tokens.
+    pub n_words: usize,
+    /// The number of words given a pretrained vector.
+    pub n_found: usize,
+    /// The number of words only found with a different case, counted in `n_found`.
+    pub n_found_case_insensitive: usize,
+    /// The number of vectors in the file.
+    pub n_vectors: usize,
+}
+
+impl EmbeddingCoverage {
+    /// The share of the words of the vocabulary given a pretrained vector, in [0, 1].
+    pub fn ratio(&self) -> f32 {
+        self.n_found as f32 / self.n_words.max(1) as f32
+    }
+}
+
+/// The pretrained vectors of the words of a vocabulary.
+#[derive(Debug, Clone)]
+pub struct PretrainedEmbeddings {
+    pub embedding_size: usize,
+    /// The vector of every word found in the file, by word index.
+    pub vectors: HashMap<u32, Vec<f32>>,
+    pub coverage: EmbeddingCoverage,
+}
+
+impl PretrainedEmbeddings {
+    /// Copy the vectors into an embedding table of shape `(vocab_size, embedding_size)`. The rows
+    /// of the special tokens and of the words without a vector are kept.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the table does not have the size of the vectors, or is
+    /// too small for the word indices.
+    pub fn initialize(&self, table: &Tensor) -> Result<Tensor, Error> {
+        let (vocab_size, embedding_size) = table.dims2()?;
+        if embedding_size != self.embedding_size {
+            anyhow::bail!(
+                "The embedding table has vectors of size {} but the pretrained vectors have size {}",
+                embedding_size,
+                self.embedding_size
+            );
+        }
+
+        let mut values = table.flatten_all()?.to_vec1::<f32>()?;
+        for (&index, vector) in &self.vectors {
+            let start = index as usize * embedding_size;
+            values
+                .get_mut(start..start + embedding_size)
+                .with_context(|| {
+                    format!(
+                        "The embedding table has {} rows, the word index {} does not fit",
+                        vocab_size, index
+                    )
+                })?
+                .copy_from_slice(vector);
+        }
+
+        Ok(Tensor::from_vec(
+            values,
+            (vocab_size, embedding_size),
+            table.device(),
+        )?)
+    }
+}
+
+/// Keeps the rows of an embedding table holding pretrained vectors fixed during training.
+///
+/// The rows of the special tokens and of the words without a vector start from a random vector,
+/// so they keep being trained. The whole table stays in the optimizer, and the frozen rows are put
+/// back to their pretrained values after every step.
+pub struct FrozenRows {
+    /// 1 in the frozen rows and 0 elsewhere, of the shape of the table.
+    mask: Tensor,
+    values: Tensor,
+    n_values: usize,
+}
+
+impl FrozenRows {
+    /// Freeze the rows of `table` holding one of the `pretrained` vectors, at their current values.
+    ///
+    /// # Arguments
+    ///
+    /// * `pretrained` - The vectors the table was initialized with.
+    /// * `table` - The embedding table of shape `(vocab_size, embedding_size)`, as returned by
+    ///   `PretrainedEmbeddings::initialize`.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the mask of the frozen rows cannot be created.
+    pub fn new(pretrained: &PretrainedEmbeddings, table: &Tensor) -> Result<Self, Error> {
+        let (vocab_size, embedding_size) = table.dims2()?;
+        let is_frozen: Vec<u8> = (0..vocab_size as u32)
+            .map(|index| u8::from(pretrained.vectors.contains_key(&index)))
+            .collect();
+        let n_rows = is_frozen
+            .iter()
+            .filter(|&&is_frozen| is_frozen == 1)
+            .count();
+
+        let mask = Tensor::from_vec(is_frozen, (vocab_size, 1), table.device())?
+            .broadcast_as((vocab_size, embedding_size))?
+            .contiguous()?;
+
+        Ok(Self {
+            mask,
+            values: table.copy()?,
+            n_values: n_rows * embedding_size,
+        })
+    }
+
+    /// The number of frozen values, i.e. not trained.
+    pub fn n_values(&self) -> usize {
+        self.n_values
+    }
+
+    /// Put the frozen rows of `table` back to their pretrained values, after an optimizer step.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if `table` does not have the shape of the frozen table.
+    pub fn restore(&self, table: &Var) -> Result<(), Error> {
+        table.set(&self.mask.where_cond(&self.values, table.as_tensor())?)?;
+        Ok(())
+    }
+}
+
+/// Matches the words of a vectors file with the words of the vocabulary.
+struct Alignment<'a> {
+    index_of: HashMap<&'a str, u32>,
+    /// The indices of the vocabulary words, by lowercased word.
+    indices_of_lowercase: HashMap<String, Vec<u32>>,
+    /// The vector of every word found, and whether it was found with the same case.
+    vectors: HashMap<u32, (Vec<f32>, bool)>,
+    n_vectors: usize,
+}
+
+impl<'a> Alignment<'a> {
+    fn new(words: &'a [String], first_index: usize) -> Self {
+        let mut index_of = HashMap::new();
+        let mut indices_of_lowercase: HashMap<String, Vec<u32>> = HashMap::new();
+        for (position, word) in words.iter().enumerate() {
+            let index = (first_index + position) as u32;
+            index_of.insert(word.as_str(), index);
+            indices_of_lowercase
+                .entry(word.to_lowercase())
+                .or_default()
+                .push(index);
+        }
+
+        Self {
+            index_of,
+            indices_of_lowercase,
+            vectors: HashMap::new(),
+            n_vectors: 0,
+        }
+    }
+
+    /// Give the vector of a word of the file to the matching vocabulary words. A word matching
+    /// with the same case replaces a vector found with a different case, otherwise the first
+    /// vector found is kept. The values are only parsed when the word is in the vocabulary.
+    fn add(
+        &mut self,
+        word: &str,
+        values: impl FnOnce() -> Result<Vec<f32>, Error>,
+    ) -> Result<(), Error> {
+        self.n_vectors += 1;
+
+        // The word itself, unless an earlier vector with the same case was already kept
+        let same_case = self
+            .index_of
+            .get(word)
+            .copied()
+            .filter(|index| !matches!(self.vectors.get(index), Some((_, true))));
+        let mut targets: Vec<(u32, bool)> =
+            same_case.map(|index| (index, true)).into_iter().collect();
+        if let Some(indices) = self.indices_of_lowercase.get(&word.to_lowercase()) {
+            targets.extend(
+                indices
+                    .iter()
+                    .filter(|&&index| {
+                        !self.vectors.contains_key(&index) && Some(index) != same_case
+                    })
+                    .map(|&index| (index, false)),
+            );
+        }
+        if targets.is_empty() {
+            return Ok(());
+        }
+
+        let vector = values()?;
+        for (index, same_case) in targets {
+            self.vectors.insert(index, (vector.clone(), same_case));
+        }
+        Ok(())
+    }
+}
+
+/// Read a text file, returning the size of its vectors.
+fn read_text(reader: impl BufRead, alignment: &mut Alignment) -> Result<usize, Error> {
+    let mut embedding_size = None;
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = line_index + 1;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+
+        // The word2vec and fastText files start with the number of words and the vector size
+        if line_number == 1
+            && fields.len() == 2
+            && fields.iter().all(|field| field.parse::<usize>().is_ok())
+        {
+            embedding_size = Some(fields[1].parse()?);
+            continue;
+        }
+
+        let size = *embedding_size.get_or_insert(fields.len() - 1);
+        if size == 0 || fields.len() <= size {
+            anyhow::bail!(
+                "Line {} has {} fields, expected a word and {} values",
+                line_number,
+                fields.len(),
+                size
+            );
+        }
+
+        // Some words contain spaces, the values are the last fields
+        let (word, values) = fields.split_at(fields.len() - size);
+        alignment.add(&word.join(" "), || {
+            values
+                .iter()
+                .map(|value| value.parse::<f32>())
+                .collect::<Result<_, _>>()
+                .with_context(|| format!("Line {} has invalid values", line_number))
+        })?;
+    }
+
+    embedding_size.context("The file does not contain any vector")
+}
+
+/// Read a word2vec binary file, returning the size of its vectors.
+fn read_binary(mut reader: impl BufRead, alignment: &mut Alignment) -> Result<usize, Error> {
+    let mut header = String::new();
+    reader.read_line(&mut header)?;
+    let sizes = header
+        .split_whitespace()
+        .map(str::parse::<usize>)
+        .collect::<Result<Vec<_>, _>>()
+        .ok()
+        .filter(|sizes| sizes.len() == 2)
+        .with_context(|| {
+            format!(
+                "Expected the number of words and the vector size, got {:?}",
+                header.trim_end()
+            )
+        })?;
+    let (n_words, embedding_size) = (sizes[0], sizes[1]);
+
+    let mut word = Vec::new();
+    let mut values = vec![0u8; embedding_size * 4];
+    for word_index in 0..n_words {
+        word.clear();
+        reader.read_until(b' ', &mut word)?;
+        if word.pop() != Some(b' ') {
+            anyhow::bail!(
+                "The file ends after {} of its {} words",
+                word_index,
+                n_words
+            );
+        }
+        reader
+            .read_exact(&mut values)
+            .with_context(|| format!("The file ends in the vector of word {}", word_index + 1))?;
+
+        // Most files end every vector with a new line
+        let word = String::from_utf8_lossy(&word);
+        alignment.add(word.trim_start_matches('\n'), || {
+            Ok(values
+                .chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect())
+        })?;
+    }
+
+    Ok(embedding_size)
+}
+
+/// Load the pretrained vectors of the words of a vocabulary.
+///
+/// The file is read once and only the vectors of the vocabulary words are kept, so that large
+/// releases such as the GloVe or fastText ones can be used. A word missing from the file gets the
+/// vector of a word differing only by case, if any.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file of pretrained vectors.
+/// * `format` - The format of the file.
+/// * `words` - The words of the vocabulary, without the special tokens.
+/// * `first_index` - The index of the first word, i.e. the number of special tokens.
+///
+/// # Errors
+///
+/// This function returns an error if the file cannot be read or is not in the given format.
+pub fn load_pretrained_embeddings(
+    path: &str,
+    format: EmbeddingsFormat,
+    words: &[String],
+    first_index: usize,
+) -> Result<PretrainedEmbeddings, Error> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open the pretrained embeddings {}", path))?;
+    let reader = BufReader::new(file);
+
+    let mut alignment = Alignment::new(words, first_index);
+    let embedding_size = match format {
+        EmbeddingsFormat::Text => read_text(reader, &mut alignment),
+        EmbeddingsFormat::Binary => read_binary(reader, &mut alignment),
+    }
+    .with_context(|| format!("Invalid pretrained embeddings {}", path))?;
+
+    let missing: Vec<&String> = words
+        .iter()
+        .enumerate()
+        .filter(|(position, _)| {
+            !alignment
+                .vectors
+                .contains_key(&((first_index + position) as u32))
+        })
+        .map(|(_, word)| word)
+        .take(20)
+        .collect();
+    log::debug!("Words without a pretrained vector: {:?}", missing);
+
+    let coverage = EmbeddingCoverage {
+        n_words: words.len(),
+        n_found: alignment.vectors.len(),
+        n_found_case_insensitive: alignment
+            .vectors
+            .values()
+            .filter(|(_, same_case)| !same_case)
+            .count(),
+        n_vectors: alignment.n_vectors,
+    };
+    let vectors = alignment
+        .vectors
+        .into_iter()
+        .map(|(index, (vector, _))| (index, vector))
+        .collect();
+
+    Ok(PretrainedEmbeddings {
+        embedding_size,
+        vectors,
+        coverage,
+    })
+}
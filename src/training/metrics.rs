@@ -12,10 +12,9 @@ use candle_core::Tensor;
 /// # Returns
 ///
 /// This function returns the count of occurrences where `predicted_value` and `actual_value` match within the nested Vecs.
-
 fn fold_with_values<T: PartialEq>(
-    predicted_labels: &Vec<Vec<T>>,
-    actual_labels: &Vec<Vec<T>>,
+    predicted_labels: &[Vec<T>],
+    actual_labels: &[Vec<T>],
     predicted_value: T,
     actual_value: T,
 ) -> usize {
@@ -55,14 +54,129 @@ pub fn f1_score(
     Ok(f1_score)
 }
 
-pub fn true_positives(predicted_labels: &Vec<Vec<f32>>, actual_labels: &Vec<Vec<f32>>) -> usize {
+pub fn true_positives(predicted_labels: &[Vec<f32>], actual_labels: &[Vec<f32>]) -> usize {
     fold_with_values(predicted_labels, actual_labels, 1., 1.)
 }
 
-pub fn false_positives(predicted_labels: &Vec<Vec<f32>>, actual_labels: &Vec<Vec<f32>>) -> usize {
+pub fn false_positives(predicted_labels: &[Vec<f32>], actual_labels: &[Vec<f32>]) -> usize {
     fold_with_values(predicted_labels, actual_labels, 1., 0.)
 }
 
-pub fn false_negatives(predicted_labels: &Vec<Vec<f32>>, actual_labels: &Vec<Vec<f32>>) -> usize {
+pub fn false_negatives(predicted_labels: &[Vec<f32>], actual_labels: &[Vec<f32>]) -> usize {
     fold_with_values(predicted_labels, actual_labels, 0., 1.)
 }
+
+/// Compute the F-beta score from the counts of a confusion matrix.
+///
+/// A `beta` below 1 favours precision, above 1 favours recall, and 1 gives the F1 score.
+///
+/// # Returns
+///
+/// The F-beta score, or 0 when there are no true positives.
+pub fn f_beta_score(
+    true_positives: usize,
+    false_positives: usize,
+    false_negatives: usize,
+    beta: f32,
+) -> f32 {
+    if true_positives == 0 {
+        return 0.;
+    }
+
+    let beta_squared = beta * beta;
+    let weighted_true_positives = (1. + beta_squared) * true_positives as f32;
+
+    weighted_true_positives
+        / (weighted_true_positives + beta_squared * false_negatives as f32 + false_positives as f32)
+}
+
+/// Search, for every class, the decision threshold maximizing the F-beta score of that class.
+///
+/// A score at or above the threshold of its class counts as a positive prediction. Among equally
+/// good candidates the one closest to `default_threshold` is kept, and classes without any positive
+/// sample keep `default_threshold`.
+///
+/// # Arguments
+///
+/// * `scores` - The predicted probabilities, one Vec of `n_classes` per sample.
+/// * `actual_labels` - The multi-hot encoded labels, one Vec of `n_classes` per sample.
+/// * `candidates` - The thresholds to try.
+/// * `beta` - The beta of the F-beta score.
+/// * `default_threshold` - The threshold used when the data does not allow to choose one.
+///
+/// # Returns
+///
+/// The threshold of every class, ordered by class index.
+pub fn calibrate_thresholds(
+    scores: &[Vec<f32>],
+    actual_labels: &[Vec<f32>],
+    candidates: &[f32],
+    beta: f32,
+    default_threshold: f32,
+) -> Vec<f32> {
+    let n_classes = actual_labels.first().map_or(0, |labels| labels.len());
+
+    (0..n_classes)
+        .map(|class| {
+            let pairs: Vec<(f32, bool)> = scores
+                .iter()
+                .zip(actual_labels.iter())
+                .map(|(scores, labels)| (scores[class], labels[class] == 1.))
+                .collect();
+
+            if !pairs.iter().any(|(_, actual)| *actual) {
+                return default_threshold;
+            }
+
+            let mut best_threshold = default_threshold;
+            let mut best_score = -1.;
+            for &threshold in candidates {
+                let count = |predicted: bool, actual: bool| {
+                    pairs
+                        .iter()
+                        .filter(|(score, is_actual)| {
+                            (*score >= threshold) == predicted && *is_actual == actual
+                        })
+                        .count()
+                };
+                let score = f_beta_score(
+                    count(true, true),
+                    count(true, false),
+                    count(false, true),
+                    beta,
+                );
+
+                let is_closer = (threshold - default_threshold).abs()
+                    < (best_threshold - default_threshold).abs();
+                if score > best_score || (score == best_score && is_closer) {
+                    best_score = score;
+                    best_threshold = threshold;
+                }
+            }
+            best_threshold
+        })
+        .collect()
+}
+
+/// Binarize scores with one threshold per class.
+///
+/// # Arguments
+///
+/// * `scores` - The predicted probabilities, one Vec of `n_classes` per sample.
+/// * `thresholds` - The threshold of every class, ordered by class index.
+///
+/// # Returns
+///
+/// The predicted labels, 1 where the score is at or above the threshold of its class and 0 otherwise.
+pub fn apply_thresholds(scores: &[Vec<f32>], thresholds: &[f32]) -> Vec<Vec<f32>> {
+    scores
+        .iter()
+        .map(|scores| {
+            scores
+                .iter()
+                .zip(thresholds.iter())
+                .map(|(score, threshold)| if score >= threshold { 1. } else { 0. })
+                .collect()
+        })
+        .collect()
+}
@@ -1 +1,4 @@
+pub mod config;
+pub mod dataset;
+pub mod embeddings;
 pub mod metrics;
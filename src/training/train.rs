@@ -1,43 +1,148 @@
 use anyhow::Result;
 use candle_core::{DType, Device, Tensor};
-
-mod config;
-mod dataset;
-mod metrics;
-mod transform;
-
-use metrics::f1_score;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use common::metrics::{
+    apply_thresholds, calibrate_thresholds, f_beta_score, false_negatives, false_positives,
+    true_positives,
+};
 
 use candle_nn::ops::sigmoid;
 use candle_nn::{loss, Optimizer, VarBuilder, VarMap};
 use candle_optimisers::adam;
 use candle_optimisers::adam::ParamsAdam;
+use clap::Parser;
+use common::dataset::{read_data, split_validation, DataLoader, Dataset};
+use common::embeddings::{load_pretrained_embeddings, FrozenRows, PretrainedEmbeddings};
+use common::training_config::{Args, Config, TrainConfig};
+use common::{build_classifier, padding_mask, ModelConfig, TextClassifier, EMBEDDING_VARIABLE};
 use common::{
-    create_class_mapping_from_labels, create_vocabulary_to_index_mapping, make_vocabulary,
-    multi_hot_encode, store_index_to_class_mapping, store_vocabulary, MODEL_PATH,
+    create_class_mapping_from_labels, create_vocabulary_to_index_mapping, encode_texts,
+    multi_hot_encode, store_index_to_class_mapping, store_model_bundle, store_vocabulary,
+    DefaultTokenizer, ModelBundle, Tokenizer, TrainingMetadata, VocabularyBuilder,
     PREDICTION_THRESHOLD,
 };
-use common::{HeadlineClassifierModel, ModelConfig};
-use config::TrainConfig;
-use dataset::{read_data, Dataset};
-use transform::encode;
+
+/// Number of candidate thresholds tried for every class, evenly spread over ]0, 1[.
+const N_THRESHOLD_CANDIDATES: usize = 19;
+
+/// The outcome of a training run.
+struct TrainingResult {
+    metadata: TrainingMetadata,
+    /// The calibrated threshold of every class, empty when calibration is disabled.
+    thresholds: Vec<f32>,
+}
+
+/// Copy the weights of every variable, which keep their values when the variables are updated.
+fn copy_weights(varmap: &VarMap) -> Result<HashMap<String, Tensor>> {
+    varmap
+        .data()
+        .lock()
+        .expect("no thread panicked holding the variables")
+        .iter()
+        .map(|(name, var)| Ok((name.clone(), var.as_tensor().copy()?)))
+        .collect()
+}
+
+/// The probabilities predicted for every sample of `data`, one Vec of `n_classes` per sample.
+fn predict_scores(
+    model: &dyn TextClassifier,
+    data: &Tensor,
+    mask: &Tensor,
+    labels: &Tensor,
+) -> Result<Vec<Vec<f32>>> {
+    let scores = sigmoid(&model.forward(data, mask)?)?;
+    if scores.dims() != labels.dims() {
+        anyhow::bail!(
+            "Model outputs of shape {:?} do not match the labels of shape {:?}",
+            scores.shape(),
+            labels.shape()
+        );
+    }
+    Ok(scores.to_vec2::<f32>()?)
+}
+
+/// The F1 score of the predictions made with one threshold per class.
+fn thresholded_f1_score(
+    scores: &[Vec<f32>],
+    actual_labels: &[Vec<f32>],
+    thresholds: &[f32],
+) -> f32 {
+    let predicted_labels = apply_thresholds(scores, thresholds);
+    f_beta_score(
+        true_positives(&predicted_labels, actual_labels),
+        false_positives(&predicted_labels, actual_labels),
+        false_negatives(&predicted_labels, actual_labels),
+        1.,
+    )
+}
 
 fn train(
     dataset: &Dataset,
     dev: &Device,
-    model_config: ModelConfig,
-    train_config: TrainConfig,
-) -> Result<()> {
+    model_config: &ModelConfig,
+    train_config: &TrainConfig,
+    pretrained_embeddings: Option<&PretrainedEmbeddings>,
+    freeze_embeddings: bool,
+    model_path: &str,
+) -> Result<TrainingResult> {
     let train_data = dataset.train_data.to_device(dev)?;
     let train_labels = dataset.train_labels.to_device(dev)?;
 
+    let validation_data = dataset.validation_data.to_device(dev)?;
+    let validation_labels = dataset.validation_labels.to_device(dev)?;
+
     let test_data = dataset.test_data.to_device(dev)?;
     let test_labels = dataset.test_labels.to_device(dev)?;
 
+    let mut train_loader = DataLoader::new(
+        train_data,
+        train_labels,
+        train_config.batch_size,
+        train_config.shuffle,
+        train_config.drop_last,
+        train_config.seed,
+    )?;
+    log::info!(
+        "Training on {} samples in {} batches per epoch.",
+        train_loader.n_samples(),
+        train_loader.n_batches()
+    );
+
     // Create a new varmap withoud loading it
-    let varmap = VarMap::new();
+    let mut varmap = VarMap::new();
     let vs = VarBuilder::from_varmap(&varmap, DType::F32, dev);
-    let model = HeadlineClassifierModel::new(&vs, &model_config)?;
+    let model = build_classifier(&vs, model_config)?;
+    let frozen_rows = match pretrained_embeddings {
+        Some(pretrained_embeddings) => {
+            let table = varmap
+                .data()
+                .lock()
+                .expect("no thread panicked holding the variables")
+                .get(EMBEDDING_VARIABLE)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("The model does not have an embedding table"))?;
+            table.set(&pretrained_embeddings.initialize(table.as_tensor())?)?;
+
+            // Only the pretrained rows are frozen, the other ones start from a random vector
+            freeze_embeddings
+                .then(|| FrozenRows::new(pretrained_embeddings, table.as_tensor()))
+                .transpose()?
+                .map(|frozen_rows| (frozen_rows, table))
+        }
+        None => None,
+    };
+    log::info!(
+        "Training a {:?} model with {} parameters, {} of them frozen.",
+        model.config().architecture,
+        model.n_parameters(),
+        frozen_rows
+            .as_ref()
+            .map_or(0, |(frozen_rows, _)| frozen_rows.n_values())
+    );
 
     let optimizer_params = adam::ParamsAdam {
         lr: train_config.learning_rate,
@@ -49,95 +154,180 @@ fn train(
     let mut optimizer = adam::Adam::new(varmap.all_vars(), optimizer_params)?;
 
     let n_epochs = train_config.n_epochs;
+    let validation_mask = padding_mask(&validation_data)?;
+    let actual_validation_labels = validation_labels.to_vec2::<f32>()?;
+    let default_thresholds = vec![PREDICTION_THRESHOLD; model_config.n_classes];
 
-    let mut best_f1_score: f32 = 0.0;
-    let mut best_model: VarMap = varmap.clone();
+    let mut best_f1_score: Option<f32> = None;
+    // The variables are updated in place, so the weights of the best epoch are copied
+    let mut best_weights: HashMap<String, Tensor> = HashMap::new();
 
     let mut early_stopping_count: u8 = 0;
+    let mut epochs_trained: u32 = 0;
 
     for epoch in 1..n_epochs + 1 {
+        epochs_trained = epoch;
 
-        // Forward the training data.
-        // PyTorch equivalent of model(...). We need to explicitly call forward in Rust.
-        // Todo - Maybe add batching here.
-        let logits = model.forward(&train_data)?.flatten(0, 1)?;
-        let loss = loss::binary_cross_entropy_with_logit(&logits, &train_labels)?;
-
-        optimizer.backward_step(&loss)?;
-
-        let test_logits = sigmoid(&model.forward(&test_data)?.flatten(0, 1)?)?;
-        
-        // Todo - Probably can do this directly on the tensors.
-        let test_predictions = test_logits
-            .to_vec2::<f32>()
-            .unwrap()
-            .iter()
-            .flat_map(|vec: &Vec<f32>| {
-                vec.iter()
-                    .map(|value: &f32| {
-                        if value >= &PREDICTION_THRESHOLD {
-                            1.0
-                        } else {
-                            0.0
-                        }
-                    })
-                    .collect::<Vec<f32>>()
-            })
-            .collect::<Vec<f32>>();
-        let (n_samples, n_classes) = test_labels.dims2()?;
-
-        let test_prediciton_tensor =
-            Tensor::from_vec(test_predictions.clone(), (n_samples, n_classes), dev)?;
-
-        log::info!("Test predictions {:?}", test_predictions);
-        log::info!(
-            "Test labels: {:?}",
-            test_labels
-                .clone()
-                .to_vec2::<f32>()?
-                .into_iter()
-                .flatten()
-                .collect::<Vec<f32>>()
+        let mut epoch_loss: f32 = 0.0;
+        let mut n_epoch_samples: usize = 0;
+
+        for batch in train_loader.epoch() {
+            let (batch_data, batch_labels) = batch?;
+
+            // Forward the batch.
+            // PyTorch equivalent of model(...). We need to explicitly call forward in Rust.
+            let logits = model.forward_t(&batch_data, &padding_mask(&batch_data)?, true)?;
+            let loss = loss::binary_cross_entropy_with_logit(&logits, &batch_labels)?;
+
+            optimizer.backward_step(&loss)?;
+            if let Some((frozen_rows, table)) = &frozen_rows {
+                frozen_rows.restore(table)?;
+            }
+
+            // Weight the mean batch loss by the batch size, so that the epoch loss is a per-sample mean.
+            let batch_size = batch_labels.dim(0)?;
+            epoch_loss += loss.to_scalar::<f32>()? * batch_size as f32;
+            n_epoch_samples += batch_size;
+        }
+        let train_loss = epoch_loss / n_epoch_samples.max(1) as f32;
+
+        let validation_scores = predict_scores(
+            model.as_ref(),
+            &validation_data,
+            &validation_mask,
+            &validation_labels,
+        )?;
+        let validation_f1_score = thresholded_f1_score(
+            &validation_scores,
+            &actual_validation_labels,
+            &default_thresholds,
         );
 
-        let test_f1_score = f1_score(&test_prediciton_tensor, &test_labels)?;
-        if test_f1_score > best_f1_score {
+        if best_f1_score.is_none_or(|best| validation_f1_score > best) {
             early_stopping_count = 0;
-            best_f1_score = test_f1_score;
-            best_model = varmap.clone();
+            best_f1_score = Some(validation_f1_score);
+            best_weights = copy_weights(&varmap)?;
         } else {
             // Store the model (essentially store the varbuilder) if ES is triggered.
             early_stopping_count += 1;
             if early_stopping_count == train_config.early_stop_patience {
                 log::warn!("Early stopping triggered.");
-                best_model.save(MODEL_PATH)?;
-                return Ok(());
+                break;
             }
         }
 
         log::info!(
-            "Epoch: {epoch:3} Train loss: {:8.5} Test F1: {:5.2}%",
-            loss.to_scalar::<f32>()?,
+            "Epoch: {epoch:3} Train loss: {:8.5} Validation F1: {:5.2}%",
+            train_loss,
+            validation_f1_score
+        );
+    }
+
+    // Never save the random initialization as if it had been trained
+    if best_weights.is_empty() {
+        anyhow::bail!(
+            "No epoch was trained out of the {} requested, the model was not saved",
+            n_epochs
+        );
+    }
+
+    // Restore and store the best model, whether or not early stopping was triggered.
+    for (name, weights) in &best_weights {
+        varmap.set_one(name, weights)?;
+    }
+    varmap.save(model_path)?;
+
+    // The test data is only used to report the scores of the best model
+    let validation_scores = predict_scores(
+        model.as_ref(),
+        &validation_data,
+        &validation_mask,
+        &validation_labels,
+    )?;
+    let test_scores = predict_scores(
+        model.as_ref(),
+        &test_data,
+        &padding_mask(&test_data)?,
+        &test_labels,
+    )?;
+    let actual_test_labels = test_labels.to_vec2::<f32>()?;
+    let test_f1_score =
+        thresholded_f1_score(&test_scores, &actual_test_labels, &default_thresholds);
+
+    let (thresholds, calibrated_f1_score) = if train_config.calibrate_thresholds {
+        let candidates: Vec<f32> = (1..=N_THRESHOLD_CANDIDATES)
+            .map(|index| index as f32 / (N_THRESHOLD_CANDIDATES + 1) as f32)
+            .collect();
+
+        let thresholds = calibrate_thresholds(
+            &validation_scores,
+            &actual_validation_labels,
+            &candidates,
+            train_config.calibration_beta,
+            PREDICTION_THRESHOLD,
+        );
+
+        let calibrated_f1_score =
+            thresholded_f1_score(&test_scores, &actual_test_labels, &thresholds);
+        log::info!(
+            "Calibrated thresholds: {:?}, test F1: {:5.2}% (was {:5.2}%)",
+            thresholds,
+            calibrated_f1_score,
             test_f1_score
         );
-    }  
+        (thresholds, calibrated_f1_score)
+    } else {
+        log::info!("Test F1: {:5.2}%", test_f1_score);
+        (Vec::new(), test_f1_score)
+    };
 
-    // If the training finishes without ES, store the model at the end.
-    best_model.save(MODEL_PATH)?;
-    Ok(())
+    let trained_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    Ok(TrainingResult {
+        metadata: TrainingMetadata {
+            trained_at,
+            n_train_samples: train_loader.n_samples(),
+            n_validation_samples: validation_labels.dim(0)?,
+            n_test_samples: test_labels.dim(0)?,
+            epochs_trained,
+            best_validation_f1: best_f1_score.unwrap_or_default(),
+            best_test_f1: test_f1_score,
+            calibrated_test_f1: calibrated_f1_score,
+            learning_rate: train_config.learning_rate,
+            batch_size: train_config.batch_size,
+        },
+        thresholds,
+    })
 }
 
 pub fn main() -> Result<()> {
     env_logger::init();
 
-    let train_config = TrainConfig::default();
-    let model_config = ModelConfig::default();
+    let config = Config::from_args(Args::parse())?;
+    let paths = &config.paths;
+    log::debug!("Config: {:?}", config);
+
+    if config.training.n_epochs == 0 {
+        anyhow::bail!("n_epochs must be at least 1");
+    }
+
+    // The output directories are not tracked, so they do not exist before the first training run
+    for output in [
+        &paths.model,
+        &paths.model_config,
+        &paths.vocabulary,
+        &paths.index_to_class,
+    ] {
+        if let Some(directory) = Path::new(output).parent() {
+            fs::create_dir_all(directory)?;
+        }
+    }
 
     let device = Device::cuda_if_available(0)?;
 
     // Load the data
-    let (train_data, train_labels) = read_data("data/train.csv")?;
-    let (test_data, test_labels) = read_data("data/test.csv")?;
+    let (train_data, train_labels) = read_data(&paths.train_data)?;
+    let (test_data, test_labels) = read_data(&paths.test_data)?;
 
     log::debug!("Train data sample: {:?}", train_data[0]);
 
@@ -145,7 +335,7 @@ pub fn main() -> Result<()> {
     let (class_to_index, index_to_class) = create_class_mapping_from_labels(&train_labels);
 
     // Store the index to class mapping for inference
-    store_index_to_class_mapping(&index_to_class, "data/index_to_class.json")?;
+    store_index_to_class_mapping(&index_to_class, &paths.index_to_class)?;
 
     log::debug!("Class to index {:?}", class_to_index);
 
@@ -153,19 +343,104 @@ pub fn main() -> Result<()> {
     let train_labels_encoded = multi_hot_encode(train_labels, &class_to_index)?;
     let test_labels_encoded = multi_hot_encode(test_labels, &class_to_index)?;
 
-    // Make the vocabulary and the vocabulary to index from the training data
-    let vocabulary = make_vocabulary(&train_data);
+    // The same tokenizer is used to build the vocabulary and to encode the data
+    let tokenizer = DefaultTokenizer::new(config.tokenizer.clone());
 
-    // Store the vocabulary to be loaded during inference
-    store_vocabulary(&vocabulary, "data/vocab.json")?;
+    // Make the vocabulary and the vocabulary to index from the training data
+    let mut vocabulary_builder = VocabularyBuilder::new()
+        .min_freq(config.vocabulary.min_freq)
+        .stop_words(config.vocabulary.stop_words.clone());
+    if let Some(max_size) = config.vocabulary.max_size {
+        vocabulary_builder = vocabulary_builder.max_size(max_size);
+    }
+    let vocabulary = vocabulary_builder.build(&train_data, &tokenizer);
+
+    // Store the vocabulary, the tokenizer configuration and the special tokens to be loaded during inference
+    store_vocabulary(&vocabulary, &paths.vocabulary)?;
+
+    let special_tokens = vocabulary.special_tokens.clone();
+
+    let vocabulary_index_mapping =
+        create_vocabulary_to_index_mapping(&vocabulary.vocabulary, &special_tokens);
+
+    if class_to_index.is_empty() {
+        anyhow::bail!("The training data does not contain any label");
+    }
+
+    // Align the pretrained vectors, if any, to the vocabulary
+    let pretrained_embeddings = match &config.embeddings.path {
+        Some(path) => {
+            let format = config.embeddings.format(path);
+            log::info!("Loading the pretrained embeddings {} ({:?})", path, format);
+            let pretrained_embeddings = load_pretrained_embeddings(
+                path,
+                format,
+                &vocabulary.vocabulary,
+                special_tokens.tokens().len(),
+            )?;
+
+            let coverage = &pretrained_embeddings.coverage;
+            log::info!(
+                "Pretrained vectors found for {} of the {} words of the vocabulary ({:.2}%), {} of them with a different case, among {} vectors",
+                coverage.n_found,
+                coverage.n_words,
+                100. * coverage.ratio(),
+                coverage.n_found_case_insensitive,
+                coverage.n_vectors
+            );
+            if pretrained_embeddings.embedding_size != config.model.embedding_size {
+                log::info!(
+                    "Using the embedding size {} of the pretrained vectors instead of {}",
+                    pretrained_embeddings.embedding_size,
+                    config.model.embedding_size
+                );
+            }
+            Some(pretrained_embeddings)
+        }
+        None => {
+            if config.embeddings.freeze {
+                log::warn!("No pretrained embeddings are set, the embeddings are not frozen.");
+            }
+            None
+        }
+    };
 
-    let vocabulary_index_mapping = create_vocabulary_to_index_mapping(&vocabulary);
+    // Size the embedding table and the classifier after the data and the pretrained vectors
+    let model_config = ModelConfig {
+        vocab_size: vocabulary.n_indices(),
+        n_classes: class_to_index.len(),
+        embedding_size: pretrained_embeddings
+            .as_ref()
+            .map_or(config.model.embedding_size, |pretrained| {
+                pretrained.embedding_size
+            }),
+        ..config.model.clone()
+    };
+    log::info!(
+        "Vocabulary size: {}, number of classes: {}",
+        model_config.vocab_size,
+        model_config.n_classes
+    );
 
     let max_seq_len = model_config.max_seq_len;
 
-    // Split string, convert to indices and pad to max length
-    let train_data_tensor = encode(&train_data, max_seq_len, &vocabulary_index_mapping, &device)?;
-    let test_data_tensor = encode(&test_data, max_seq_len, &vocabulary_index_mapping, &device)?;
+    // Tokenize, convert to indices and pad to max length, giving (n_samples, max_seq_len) tensors
+    let train_data_tensor = encode_texts(
+        &train_data,
+        &tokenizer,
+        &vocabulary_index_mapping,
+        &special_tokens,
+        max_seq_len,
+        &device,
+    )?;
+    let test_data_tensor = encode_texts(
+        &test_data,
+        &tokenizer,
+        &vocabulary_index_mapping,
+        &special_tokens,
+        max_seq_len,
+        &device,
+    )?;
 
     let n_classes = model_config.n_classes;
 
@@ -191,15 +466,61 @@ pub fn main() -> Result<()> {
     log::debug!("Test data tensor: {:?}", test_data_tensor);
     log::debug!("Test labels tensor: {:?}", test_labels_tensor);
 
+    // Hold out part of the training data to select the best epoch and calibrate the thresholds,
+    // so that the test data is only used to report the scores
+    let (
+        (train_data_tensor, train_labels_tensor),
+        (validation_data_tensor, validation_labels_tensor),
+    ) = split_validation(
+        &train_data_tensor,
+        &train_labels_tensor,
+        config.training.validation_fraction,
+        config.training.seed,
+    )?;
+
     let dataset = Dataset {
         train_data: train_data_tensor,
         train_labels: train_labels_tensor,
+        validation_data: validation_data_tensor,
+        validation_labels: validation_labels_tensor,
         test_data: test_data_tensor,
         test_labels: test_labels_tensor,
     };
 
+    // Make sure every encoded index fits in the embedding table before training starts
+    dataset.validate(model_config.vocab_size, model_config.n_classes)?;
+
+    // Write the effective config, with the derived model sizes, next to the model for reproducibility
+    let effective_config = Config {
+        model: model_config.clone(),
+        ..config.clone()
+    };
+    let effective_config_path = effective_config.store_effective()?;
+    log::info!("Effective config written to {}", effective_config_path);
+
     log::info!("Started training.");
-    train(&dataset, &device, model_config, train_config)?;
+    let training_result = train(
+        &dataset,
+        &device,
+        &model_config,
+        &config.training,
+        pretrained_embeddings.as_ref(),
+        config.embeddings.freeze,
+        &paths.model,
+    )?;
+
+    // Store the configuration needed to rebuild the model next to its weights
+    let labels = (0..index_to_class.len() as u32)
+        .map(|index| index_to_class[&index].clone())
+        .collect();
+    let bundle = ModelBundle {
+        model: model_config,
+        tokenizer: tokenizer.config().clone(),
+        labels,
+        thresholds: training_result.thresholds,
+        training: training_result.metadata,
+    };
+    store_model_bundle(&bundle, &paths.model_config)?;
 
     Ok(())
 }
@@ -0,0 +1,154 @@
+#[cfg(test)]
+mod test_artifact {
+
+    use std::collections::HashMap;
+
+    use candle_core::{DType, Device};
+    use candle_nn::{VarBuilder, VarMap};
+    use common::*;
+
+    fn make_bundle() -> ModelBundle {
+        ModelBundle {
+            model: ModelConfig {
+                vocab_size: make_vocabulary().n_indices(),
+                embedding_size: 4,
+                hidden_size: 5,
+                ..ModelConfig::default()
+            },
+            tokenizer: TokenizerConfig::default(),
+            labels: vec!["sports".to_string(), "weather".to_string()],
+            thresholds: vec![0.3, 0.6],
+            training: TrainingMetadata::default(),
+        }
+    }
+
+    fn make_vocabulary() -> Vocabulary {
+        let corpus = vec!["storm hits coast".to_string()];
+        VocabularyBuilder::new().build(&corpus, &DefaultTokenizer::default())
+    }
+
+    fn make_index_to_class() -> HashMap<u32, String> {
+        [(0, "sports".to_string()), (1, "weather".to_string())]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_store_and_load_model_bundle() {
+        let path = std::env::temp_dir().join("test_store_and_load_model_bundle.json");
+        let path = path.to_str().unwrap();
+        let bundle = make_bundle();
+
+        store_model_bundle(&bundle, path).unwrap();
+        let loaded_bundle = load_model_bundle(path).unwrap();
+
+        assert_eq!(bundle.labels, loaded_bundle.labels);
+        assert_eq!(bundle.model.vocab_size, loaded_bundle.model.vocab_size);
+        assert_eq!(bundle.model.architecture, loaded_bundle.model.architecture);
+        assert_eq!(bundle.tokenizer, loaded_bundle.tokenizer);
+        assert_eq!(bundle.thresholds, loaded_bundle.thresholds);
+    }
+
+    #[test]
+    fn test_bundle_without_thresholds_uses_global_threshold() {
+        let mut json = serde_json::to_value(make_bundle()).unwrap();
+        json.as_object_mut().unwrap().remove("thresholds");
+
+        let bundle: ModelBundle = serde_json::from_value(json).unwrap();
+
+        assert!(bundle.thresholds.is_empty());
+        assert_eq!(
+            vec![PREDICTION_THRESHOLD, PREDICTION_THRESHOLD],
+            bundle.class_thresholds()
+        );
+        assert_eq!(vec![0.3, 0.6], make_bundle().class_thresholds());
+    }
+
+    #[test]
+    fn test_validate_threshold_count_mismatch() {
+        let bundle = ModelBundle {
+            thresholds: vec![0.5],
+            ..make_bundle()
+        };
+
+        let error = bundle
+            .validate(&make_vocabulary(), &make_index_to_class())
+            .unwrap_err();
+
+        assert!(error.to_string().contains("thresholds"));
+    }
+
+    #[test]
+    fn test_validate_matching_artifacts() {
+        let bundle = make_bundle();
+
+        assert!(bundle
+            .validate(&make_vocabulary(), &make_index_to_class())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_validate_label_mismatch() {
+        let bundle = make_bundle();
+        let index_to_class: HashMap<u32, String> =
+            [(0, "weather".to_string())].into_iter().collect();
+
+        assert!(bundle
+            .validate(&make_vocabulary(), &index_to_class)
+            .is_err());
+    }
+
+    #[test]
+    fn test_validate_vocabulary_too_large() {
+        let bundle = ModelBundle {
+            model: ModelConfig {
+                vocab_size: 3,
+                ..make_bundle().model
+            },
+            ..make_bundle()
+        };
+
+        let error = bundle
+            .validate(&make_vocabulary(), &make_index_to_class())
+            .unwrap_err();
+
+        assert!(error.to_string().contains("embedding table"));
+    }
+
+    #[test]
+    fn test_validate_vocabulary_too_small() {
+        let bundle = ModelBundle {
+            model: ModelConfig {
+                vocab_size: make_vocabulary().n_indices() + 1,
+                ..make_bundle().model
+            },
+            ..make_bundle()
+        };
+
+        let error = bundle
+            .validate(&make_vocabulary(), &make_index_to_class())
+            .unwrap_err();
+
+        assert!(error.to_string().contains("embedding table"));
+    }
+
+    #[test]
+    fn test_load_model_shape_mismatch() {
+        let path = std::env::temp_dir().join("test_load_model_shape_mismatch.bin");
+        let path = path.to_str().unwrap();
+        let config = make_bundle().model;
+
+        let varmap = VarMap::new();
+        let vs = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        build_classifier(&vs, &config).unwrap();
+        varmap.save(path).unwrap();
+
+        assert!(load_model(path, &config).is_ok());
+
+        let mismatched_config = ModelConfig {
+            embedding_size: 8,
+            ..config
+        };
+        assert!(load_model(path, &mismatched_config).is_err());
+    }
+}
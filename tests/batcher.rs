@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod test_batcher {
+
+    use common::batcher::MicroBatcher;
+    use common::error::ServingError;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// A batcher doubling its inputs, recording the size of every batch it runs.
+    fn doubling_batcher(
+        max_batch_size: usize,
+        max_wait: Duration,
+    ) -> (MicroBatcher<u32, u32>, Arc<Mutex<Vec<usize>>>) {
+        let batch_sizes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&batch_sizes);
+        let batcher = MicroBatcher::new(max_batch_size, max_wait, 64, move |inputs: Vec<u32>| {
+            recorded.lock().unwrap().push(inputs.len());
+            async move { Ok(inputs.into_iter().map(|input| input * 2).collect()) }
+        });
+        (batcher, batch_sizes)
+    }
+
+    async fn submit_all(batcher: &MicroBatcher<u32, u32>, inputs: &[u32]) -> Vec<u32> {
+        let handles: Vec<_> = inputs
+            .iter()
+            .map(|&input| {
+                let batcher = batcher.clone();
+                tokio::spawn(async move { batcher.submit(input).await })
+            })
+            .collect();
+
+        let mut outputs = Vec::new();
+        for handle in handles {
+            outputs.push(handle.await.unwrap().unwrap());
+        }
+        outputs
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_inputs_share_a_batch() {
+        let (batcher, batch_sizes) = doubling_batcher(4, Duration::from_millis(200));
+
+        let outputs = submit_all(&batcher, &[1, 2, 3, 4]).await;
+
+        assert_eq!(vec![2, 4, 6, 8], outputs);
+        assert_eq!(vec![4], *batch_sizes.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_batches_are_capped_at_max_batch_size() {
+        let (batcher, batch_sizes) = doubling_batcher(2, Duration::from_millis(200));
+
+        let outputs = submit_all(&batcher, &[1, 2, 3, 4, 5]).await;
+
+        assert_eq!(vec![2, 4, 6, 8, 10], outputs);
+        let batch_sizes = batch_sizes.lock().unwrap();
+        assert_eq!(5, batch_sizes.iter().sum::<usize>());
+        assert!(batch_sizes.iter().all(|&size| size <= 2));
+    }
+
+    #[tokio::test]
+    async fn test_single_input_is_not_held_past_max_wait() {
+        let (batcher, batch_sizes) = doubling_batcher(16, Duration::from_millis(10));
+
+        let output = tokio::time::timeout(Duration::from_secs(1), batcher.submit(21))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(42, output);
+        assert_eq!(vec![1], *batch_sizes.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_batch_error_is_returned_to_every_caller() {
+        let batcher: MicroBatcher<u32, u32> = MicroBatcher::new(
+            2,
+            Duration::from_millis(200),
+            64,
+            |_inputs: Vec<u32>| async {
+                Err(ServingError::Model("Forward pass failed".to_string()))
+            },
+        );
+
+        let first = tokio::spawn({
+            let batcher = batcher.clone();
+            async move { batcher.submit(1).await }
+        });
+        let second = batcher.submit(2).await;
+
+        assert!(matches!(first.await.unwrap(), Err(ServingError::Model(_))));
+        assert!(matches!(second, Err(ServingError::Model(_))));
+    }
+}
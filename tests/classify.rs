@@ -0,0 +1,74 @@
+mod support;
+
+#[cfg(test)]
+mod test_classify {
+
+    use super::support::{temp_dir, write_artifacts};
+    use common::classify::predict_batch;
+    use common::input::Record;
+    use common::loader::load_shared_data;
+    use common::monitoring::ServingMetrics;
+    use common::types::PredictionOptions;
+
+    fn record(id: &str, text: &str) -> Record {
+        Record {
+            id: Some(id.to_string()),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_predict_batch_skips_the_invalid_texts() {
+        let paths = write_artifacts(
+            &temp_dir("test_predict_batch_skips_the_invalid_texts"),
+            &["sports", "weather"],
+        );
+        let data = load_shared_data(&paths).unwrap();
+        let metrics = ServingMetrics::new().unwrap();
+        let records = vec![
+            record("1", "Storm hits the coast"),
+            record("2", "   "),
+            record("3", "!!!"),
+            record("4", "Heavy rain"),
+        ];
+
+        let predictions =
+            predict_batch(records, &data, &PredictionOptions::default(), &metrics).unwrap();
+
+        // Every record gets a prediction, in order, the invalid ones without scores nor labels
+        let ids: Vec<&str> = predictions
+            .iter()
+            .map(|prediction| prediction.id.as_deref().unwrap())
+            .collect();
+        assert_eq!(vec!["1", "2", "3", "4"], ids);
+        let has_scores: Vec<bool> = predictions
+            .iter()
+            .map(|prediction| prediction.scores.is_some())
+            .collect();
+        assert_eq!(vec![true, false, false, true], has_scores);
+        assert!(predictions[1].labels.is_empty());
+        assert!(predictions[2].labels.is_empty());
+        assert_eq!(2, predictions[0].scores.as_ref().unwrap().len());
+    }
+
+    #[test]
+    fn test_predict_batch_applies_the_threshold() {
+        let paths = write_artifacts(
+            &temp_dir("test_predict_batch_applies_the_threshold"),
+            &["sports", "weather"],
+        );
+        let data = load_shared_data(&paths).unwrap();
+        let metrics = ServingMetrics::new().unwrap();
+        let predict = |threshold: f32| {
+            let options = PredictionOptions {
+                threshold: Some(threshold),
+                ..Default::default()
+            };
+            predict_batch(vec![record("1", "Storm")], &data, &options, &metrics).unwrap()
+        };
+
+        // The scores of an untrained model are neither 0 nor 1
+        assert_eq!(2, predict(0.).remove(0).labels.len());
+        assert!(predict(1.).remove(0).labels.is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+#[cfg(test)]
+mod test_dataset {
+
+    use candle_core::{Device, Tensor};
+    use common::dataset::*;
+
+    fn make_loader(batch_size: usize, shuffle: bool, drop_last: bool, seed: u64) -> DataLoader {
+        // 5 samples with a sequence length of 2, laid out as (n_samples, max_seq_len).
+        let data = Tensor::new(
+            &[[0u32, 10], [1, 11], [2, 12], [3, 13], [4, 14]],
+            &Device::Cpu,
+        )
+        .unwrap();
+        let labels = Tensor::new(&[[0f32], [1.], [2.], [3.], [4.]], &Device::Cpu).unwrap();
+        DataLoader::new(data, labels, batch_size, shuffle, drop_last, seed).unwrap()
+    }
+
+    fn collect_labels(loader: &mut DataLoader) -> Vec<Vec<f32>> {
+        loader
+            .epoch()
+            .map(|batch| {
+                let (_, labels) = batch.unwrap();
+                labels.flatten_all().unwrap().to_vec1::<f32>().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_data_loader_sequential_batches() {
+        let mut loader = make_loader(2, false, false, 0);
+
+        let actual_result = collect_labels(&mut loader);
+        let expected_result = vec![vec![0., 1.], vec![2., 3.], vec![4.]];
+
+        assert_eq!(expected_result, actual_result);
+        assert_eq!(3, loader.n_batches());
+    }
+
+    #[test]
+    fn test_data_loader_drop_last() {
+        let mut loader = make_loader(2, false, true, 0);
+
+        let actual_result = collect_labels(&mut loader);
+        let expected_result = vec![vec![0., 1.], vec![2., 3.]];
+
+        assert_eq!(expected_result, actual_result);
+        assert_eq!(2, loader.n_batches());
+    }
+
+    #[test]
+    fn test_data_loader_batches_keep_data_and_labels_aligned() {
+        let mut loader = make_loader(2, true, false, 7);
+
+        for batch in loader.epoch() {
+            let (data, labels) = batch.unwrap();
+            let first_positions = data.narrow(1, 0, 1).unwrap().squeeze(1).unwrap();
+            let first_positions = first_positions.to_vec1::<u32>().unwrap();
+            let labels = labels.flatten_all().unwrap().to_vec1::<f32>().unwrap();
+            let expected_labels: Vec<f32> = first_positions.iter().map(|&i| i as f32).collect();
+            assert_eq!(expected_labels, labels);
+        }
+    }
+
+    #[test]
+    fn test_data_loader_shuffle_is_seeded() {
+        let mut first_loader = make_loader(5, true, false, 42);
+        let mut second_loader = make_loader(5, true, false, 42);
+
+        let mut first_epoch = collect_labels(&mut first_loader).concat();
+        assert_eq!(collect_labels(&mut second_loader).concat(), first_epoch);
+
+        first_epoch.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(vec![0., 1., 2., 3., 4.], first_epoch);
+    }
+
+    #[test]
+    fn test_data_loader_rejects_mismatched_samples() {
+        let data = Tensor::zeros((3, 2), candle_core::DType::U32, &Device::Cpu).unwrap();
+        let labels = Tensor::zeros((2, 1), candle_core::DType::F32, &Device::Cpu).unwrap();
+
+        assert!(DataLoader::new(data, labels, 2, false, false, 0).is_err());
+    }
+
+    fn make_samples() -> (Tensor, Tensor) {
+        let data = Tensor::new(
+            &[[0u32, 10], [1, 11], [2, 12], [3, 13], [4, 14]],
+            &Device::Cpu,
+        )
+        .unwrap();
+        let labels = Tensor::new(&[[0f32], [1.], [2.], [3.], [4.]], &Device::Cpu).unwrap();
+        (data, labels)
+    }
+
+    fn sample_ids(labels: &Tensor) -> Vec<f32> {
+        labels.flatten_all().unwrap().to_vec1::<f32>().unwrap()
+    }
+
+    #[test]
+    fn test_split_validation_holds_out_a_share_of_the_samples() {
+        let (data, labels) = make_samples();
+
+        let ((train_data, train_labels), (validation_data, validation_labels)) =
+            split_validation(&data, &labels, 0.4, 42).unwrap();
+
+        assert_eq!(3, train_data.dim(0).unwrap());
+        assert_eq!(2, validation_data.dim(0).unwrap());
+
+        // Every sample lands in exactly one part, with its own labels
+        let mut ids = [sample_ids(&train_labels), sample_ids(&validation_labels)].concat();
+        ids.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(vec![0., 1., 2., 3., 4.], ids);
+        let first_tokens = validation_data
+            .get_on_dim(1, 0)
+            .unwrap()
+            .to_vec1::<u32>()
+            .unwrap();
+        let validation_ids: Vec<u32> = sample_ids(&validation_labels)
+            .into_iter()
+            .map(|id| id as u32)
+            .collect();
+        assert_eq!(validation_ids, first_tokens);
+    }
+
+    #[test]
+    fn test_split_validation_is_seeded() {
+        let (data, labels) = make_samples();
+
+        let (_, (_, first)) = split_validation(&data, &labels, 0.4, 42).unwrap();
+        let (_, (_, second)) = split_validation(&data, &labels, 0.4, 42).unwrap();
+
+        assert_eq!(sample_ids(&first), sample_ids(&second));
+    }
+
+    #[test]
+    fn test_split_validation_keeps_a_sample_in_each_part() {
+        let (data, labels) = make_samples();
+
+        let ((small_train, _), (small_validation, _)) =
+            split_validation(&data, &labels, 0.01, 0).unwrap();
+        let ((large_train, _), (large_validation, _)) =
+            split_validation(&data, &labels, 0.99, 0).unwrap();
+
+        assert_eq!(
+            (4, 1),
+            (
+                small_train.dim(0).unwrap(),
+                small_validation.dim(0).unwrap()
+            )
+        );
+        assert_eq!(
+            (1, 4),
+            (
+                large_train.dim(0).unwrap(),
+                large_validation.dim(0).unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_split_validation_rejects_invalid_inputs() {
+        let (data, labels) = make_samples();
+
+        assert!(split_validation(&data, &labels, 0., 0).is_err());
+        assert!(split_validation(&data, &labels, 1., 0).is_err());
+
+        let single = data.narrow(0, 0, 1).unwrap();
+        let single_labels = labels.narrow(0, 0, 1).unwrap();
+        assert!(split_validation(&single, &single_labels, 0.5, 0).is_err());
+
+        assert!(split_validation(&single, &labels, 0.5, 0).is_err());
+    }
+
+    fn make_dataset(max_index: u32, n_classes: usize) -> Dataset {
+        let data = Tensor::new(&[[2u32, max_index], [3, 0]], &Device::Cpu).unwrap();
+        let labels = Tensor::zeros((2, n_classes), candle_core::DType::F32, &Device::Cpu).unwrap();
+        Dataset {
+            train_data: data.clone(),
+            train_labels: labels.clone(),
+            validation_data: data.clone(),
+            validation_labels: labels.clone(),
+            test_data: data,
+            test_labels: labels,
+        }
+    }
+
+    #[test]
+    fn test_dataset_validate_ok() {
+        let dataset = make_dataset(9, 3);
+
+        assert!(dataset.validate(10, 3).is_ok());
+    }
+
+    #[test]
+    fn test_dataset_validate_index_out_of_range() {
+        let dataset = make_dataset(10, 3);
+
+        let error = dataset.validate(10, 3).unwrap_err();
+
+        assert!(error.to_string().contains("token index 10"));
+    }
+
+    #[test]
+    fn test_dataset_validate_class_mismatch() {
+        let dataset = make_dataset(9, 3);
+
+        assert!(dataset.validate(10, 2).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+#[cfg(test)]
+mod test_embeddings {
+
+    use candle_core::{Device, Tensor, Var};
+    use candle_nn::Optimizer;
+    use candle_optimisers::adam::{Adam, ParamsAdam};
+    use common::embeddings::*;
+    use std::fs;
+
+    /// The words of the vocabulary, after the two special tokens.
+    fn make_words() -> Vec<String> {
+        ["blizzard", "hits", "paris", "team"]
+            .iter()
+            .map(|word| word.to_string())
+            .collect()
+    }
+
+    fn write_file(name: &str, content: &[u8]) -> String {
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_load_glove_text_file() {
+        let path = write_file(
+            "test_load_glove_text_file.txt",
+            b"the 0.1 0.2 0.3\nblizzard 1 2 3\nteam -1 -2 -3.5\n",
+        );
+
+        let embeddings =
+            load_pretrained_embeddings(&path, EmbeddingsFormat::Text, &make_words(), 2).unwrap();
+
+        assert_eq!(3, embeddings.embedding_size);
+        assert_eq!(Some(&vec![1., 2., 3.]), embeddings.vectors.get(&2));
+        assert_eq!(Some(&vec![-1., -2., -3.5]), embeddings.vectors.get(&5));
+        assert_eq!(
+            EmbeddingCoverage {
+                n_words: 4,
+                n_found: 2,
+                n_found_case_insensitive: 0,
+                n_vectors: 3,
+            },
+            embeddings.coverage
+        );
+        assert_eq!(0.5, embeddings.coverage.ratio());
+    }
+
+    #[test]
+    fn test_load_text_file_with_header_prefers_same_case() {
+        // The word2vec and fastText header, then words of different cases
+        let path = write_file(
+            "test_load_text_file_with_header_prefers_same_case.vec",
+            b"4 2\nHits 9 9\nParis 1 1\nhits 2 2 \nHITS 3 3\n",
+        );
+
+        let embeddings =
+            load_pretrained_embeddings(&path, EmbeddingsFormat::Text, &make_words(), 2).unwrap();
+
+        assert_eq!(2, embeddings.embedding_size);
+        assert_eq!(Some(&vec![2., 2.]), embeddings.vectors.get(&3));
+        assert_eq!(Some(&vec![1., 1.]), embeddings.vectors.get(&4));
+        assert_eq!(2, embeddings.coverage.n_found);
+        assert_eq!(1, embeddings.coverage.n_found_case_insensitive);
+    }
+
+    #[test]
+    fn test_load_word2vec_binary_file() {
+        let mut content = b"2 2\n".to_vec();
+        for (word, values) in [("team", [0.5f32, -1.]), ("blizzard", [2., 4.])] {
+            content.extend(word.as_bytes());
+            content.push(b' ');
+            for value in values {
+                content.extend(value.to_le_bytes());
+            }
+            content.push(b'\n');
+        }
+        let path = write_file("test_load_word2vec_binary_file.bin", &content);
+
+        let embeddings =
+            load_pretrained_embeddings(&path, EmbeddingsFormat::from_path(&path), &make_words(), 2)
+                .unwrap();
+
+        assert_eq!(Some(&vec![2., 4.]), embeddings.vectors.get(&2));
+        assert_eq!(Some(&vec![0.5, -1.]), embeddings.vectors.get(&5));
+        assert_eq!(2, embeddings.coverage.n_vectors);
+    }
+
+    #[test]
+    fn test_load_rejects_vectors_of_different_sizes() {
+        let path = write_file(
+            "test_load_rejects_vectors_of_different_sizes.txt",
+            b"the 0.1 0.2 0.3\nteam 1 2\n",
+        );
+
+        let error = load_pretrained_embeddings(&path, EmbeddingsFormat::Text, &make_words(), 2)
+            .unwrap_err();
+
+        assert!(format!("{:#}", error).contains("Line 2"));
+    }
+
+    #[test]
+    fn test_initialize_keeps_rows_without_vectors() {
+        let path = write_file(
+            "test_initialize_keeps_rows_without_vectors.txt",
+            b"hits 1 2\n",
+        );
+        let embeddings =
+            load_pretrained_embeddings(&path, EmbeddingsFormat::Text, &make_words(), 2).unwrap();
+        let table = Tensor::zeros((6, 2), candle_core::DType::F32, &Device::Cpu).unwrap();
+
+        let table = embeddings.initialize(&table).unwrap();
+
+        let rows = table.to_vec2::<f32>().unwrap();
+        assert_eq!(vec![1., 2.], rows[3]);
+        assert!(rows
+            .iter()
+            .enumerate()
+            .all(|(index, row)| index == 3 || row == &vec![0., 0.]));
+
+        let too_small = Tensor::zeros((6, 3), candle_core::DType::F32, &Device::Cpu).unwrap();
+        assert!(embeddings.initialize(&too_small).is_err());
+    }
+
+    #[test]
+    fn test_optimizer_step_leaves_the_frozen_rows_unchanged() {
+        let path = write_file(
+            "test_optimizer_step_leaves_the_frozen_rows_unchanged.txt",
+            b"hits 1 2\nteam 3 4\n",
+        );
+        let embeddings =
+            load_pretrained_embeddings(&path, EmbeddingsFormat::Text, &make_words(), 2).unwrap();
+        let random = Tensor::rand(-1f32, 1., (6, 2), &Device::Cpu).unwrap();
+        let table = Var::from_tensor(&embeddings.initialize(&random).unwrap()).unwrap();
+        let initial = table.as_tensor().to_vec2::<f32>().unwrap();
+        let frozen_rows = FrozenRows::new(&embeddings, table.as_tensor()).unwrap();
+        let mut optimizer = Adam::new(
+            vec![table.clone()],
+            ParamsAdam {
+                lr: 0.1,
+                ..ParamsAdam::default()
+            },
+        )
+        .unwrap();
+
+        // Every value has a gradient
+        let loss = table.as_tensor().sqr().unwrap().sum_all().unwrap();
+        optimizer.backward_step(&loss).unwrap();
+        frozen_rows.restore(&table).unwrap();
+
+        let rows = table.as_tensor().to_vec2::<f32>().unwrap();
+        assert_eq!(vec![1., 2.], rows[3]);
+        assert_eq!(vec![3., 4.], rows[5]);
+        // The special tokens and the words without a vector are trained
+        for index in [0, 1, 2, 4] {
+            assert_ne!(initial[index], rows[index], "row {}", index);
+        }
+        assert_eq!(4, frozen_rows.n_values());
+    }
+
+    #[test]
+    fn test_format_is_guessed_from_the_extension() {
+        assert_eq!(
+            EmbeddingsFormat::Binary,
+            EmbeddingsFormat::from_path("GoogleNews-vectors.BIN")
+        );
+        assert_eq!(
+            EmbeddingsFormat::Text,
+            EmbeddingsFormat::from_path("glove.6B.100d.txt")
+        );
+        assert_eq!(
+            EmbeddingsFormat::Text,
+            EmbeddingsFormat::from_path("wiki.en.vec")
+        );
+    }
+}
@@ -4,6 +4,7 @@ mod encode {
     use std::collections::HashMap;
 
     use common::encode::*;
+    use common::vocabulary::SpecialTokens;
 
     #[test]
     fn test_create_vocabulary_to_index_mapping_multiple_words() {
@@ -12,12 +13,13 @@ mod encode {
             "banana".to_string(),
             "cherry".to_string(),
         ];
-        let result = create_vocabulary_to_index_mapping(&vocabulary);
+        let result = create_vocabulary_to_index_mapping(&vocabulary, &SpecialTokens::default());
         let expected: HashMap<String, u32> = [
-            ("<UNK>".to_string(), 0),
-            ("apple".to_string(), 1),
-            ("banana".to_string(), 2),
-            ("cherry".to_string(), 3),
+            ("<PAD>".to_string(), 0),
+            ("<UNK>".to_string(), 1),
+            ("apple".to_string(), 2),
+            ("banana".to_string(), 3),
+            ("cherry".to_string(), 4),
         ]
         .iter()
         .cloned()
@@ -33,17 +35,92 @@ mod encode {
             "banana".to_string(),
             "cherry".to_string(),
         ];
-        let mapping: HashMap<String, u32> = [("apple".to_string(), 1), ("cherry".to_string(), 3)]
+        let mapping: HashMap<String, u32> = [("apple".to_string(), 2), ("cherry".to_string(), 4)]
             .iter()
             .cloned()
             .collect();
 
         let result = map_to_indices(words, &mapping);
-        let expected: Vec<u32> = vec![1, 0, 3];
+        let expected: Vec<u32> = vec![2, UNK_INDEX, 4];
 
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_create_vocabulary_to_index_mapping_with_bos_eos() {
+        let vocabulary = vec!["apple".to_string()];
+        let special_tokens = SpecialTokens {
+            bos: Some(BOS_TOKEN.to_string()),
+            eos: Some(EOS_TOKEN.to_string()),
+            ..SpecialTokens::default()
+        };
+        let result = create_vocabulary_to_index_mapping(&vocabulary, &special_tokens);
+        let expected: HashMap<String, u32> = [
+            ("<PAD>".to_string(), 0),
+            ("<UNK>".to_string(), 1),
+            ("<BOS>".to_string(), 2),
+            ("<EOS>".to_string(), 3),
+            ("apple".to_string(), 4),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_encode_sequence_pads_with_pad_index() {
+        let vocabulary = vec!["apple".to_string(), "banana".to_string()];
+        let special_tokens = SpecialTokens::default();
+        let mapping = create_vocabulary_to_index_mapping(&vocabulary, &special_tokens);
+        let words = vec!["banana".to_string(), "kiwi".to_string()];
+
+        let result = encode_sequence(words, &mapping, &special_tokens, 5);
+        let expected: Vec<u32> = vec![3, UNK_INDEX, PAD_INDEX, PAD_INDEX, PAD_INDEX];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_encode_sequence_keeps_markers_when_truncating() {
+        let vocabulary = vec!["apple".to_string(), "banana".to_string()];
+        let special_tokens = SpecialTokens {
+            bos: Some(BOS_TOKEN.to_string()),
+            eos: Some(EOS_TOKEN.to_string()),
+            ..SpecialTokens::default()
+        };
+        let mapping = create_vocabulary_to_index_mapping(&vocabulary, &special_tokens);
+        let words = vec![
+            "apple".to_string(),
+            "banana".to_string(),
+            "apple".to_string(),
+        ];
+
+        let result = encode_sequence(words, &mapping, &special_tokens, 4);
+        let expected: Vec<u32> = vec![2, 4, 5, 3];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_max_sequence_words_leaves_room_for_the_markers() {
+        let vocabulary = vec!["apple".to_string()];
+        let special_tokens = SpecialTokens {
+            bos: Some(BOS_TOKEN.to_string()),
+            eos: Some(EOS_TOKEN.to_string()),
+            ..SpecialTokens::default()
+        };
+        let mapping = create_vocabulary_to_index_mapping(&vocabulary, &special_tokens);
+
+        assert_eq!(2, max_sequence_words(&mapping, &special_tokens, 4));
+        assert_eq!(0, max_sequence_words(&mapping, &special_tokens, 1));
+        assert_eq!(
+            4,
+            max_sequence_words(&mapping, &SpecialTokens::default(), 4)
+        );
+    }
+
     #[test]
     fn test_map_to_indices_empty_words() {
         let words: Vec<String> = Vec::new();
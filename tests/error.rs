@@ -0,0 +1,152 @@
+#[cfg(test)]
+mod test_error {
+
+    use common::error::*;
+    use serde_json::Value;
+    use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+    async fn reply_for(rejection: Rejection) -> (StatusCode, Value) {
+        let response = handle_rejection(rejection).await.unwrap().into_response();
+        let status = response.status();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    async fn assert_error(error: ServingError, expected_status: StatusCode, expected_code: &str) {
+        let (status, body) = reply_for(warp::reject::custom(error)).await;
+
+        assert_eq!(expected_status, status);
+        assert_eq!(expected_code, body["error"]["code"]);
+        assert!(body["error"]["message"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_validation_error() {
+        assert_error(
+            ServingError::Validation("empty".to_string()),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation_error",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_tokenization_error() {
+        assert_error(
+            ServingError::Tokenization("no tokens".to_string()),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "tokenization_error",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_batch_too_large_error() {
+        let (status, body) = reply_for(warp::reject::custom(ServingError::BatchTooLarge {
+            size: 3,
+            max_size: 2,
+        }))
+        .await;
+
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, status);
+        assert_eq!("batch_too_large", body["error"]["code"]);
+        assert_eq!(
+            "Batch of 3 items exceeds the maximum batch size of 2",
+            body["error"]["message"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tensor_and_model_errors() {
+        assert_error(
+            ServingError::Tensor("shape".to_string()),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "tensor_error",
+        )
+        .await;
+        assert_error(
+            ServingError::Model("forward".to_string()),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "model_error",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_error() {
+        assert_error(
+            ServingError::Unavailable("busy".to_string()),
+            StatusCode::SERVICE_UNAVAILABLE,
+            "unavailable",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_error() {
+        assert_error(
+            ServingError::Unauthorized("token".to_string()),
+            StatusCode::UNAUTHORIZED,
+            "unauthorized",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_model_not_found_error() {
+        assert_error(
+            ServingError::ModelNotFound("sections".to_string()),
+            StatusCode::NOT_FOUND,
+            "model_not_found",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_reload_error() {
+        assert_error(
+            ServingError::Reload("missing weights".to_string()),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "reload_error",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json_is_bad_request() {
+        let filter = warp::body::json::<Value>();
+        let rejection = warp::test::request()
+            .method("POST")
+            .body("{not json")
+            .filter(&filter)
+            .await
+            .unwrap_err();
+
+        let (status, body) = reply_for(rejection).await;
+
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+        assert_eq!("bad_request", body["error"]["code"]);
+    }
+
+    #[tokio::test]
+    async fn test_recovered_route() {
+        let route = warp::path("predict")
+            .and_then(|| async {
+                Err::<String, _>(warp::reject::custom(ServingError::Validation(
+                    "The text of item 0 is empty".to_string(),
+                )))
+            })
+            .recover(handle_rejection);
+
+        let response = warp::test::request().path("/predict").reply(&route).await;
+        let body: Value = serde_json::from_slice(response.body()).unwrap();
+
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status());
+        assert_eq!("The text of item 0 is empty", body["error"]["message"]);
+
+        let response = warp::test::request().path("/unknown").reply(&route).await;
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+    }
+}
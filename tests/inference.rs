@@ -0,0 +1,362 @@
+#[cfg(test)]
+mod test_inference {
+
+    use common::error::ServingError;
+    use common::inference::{score_labels, select_labels, to_v1_response, token_stats};
+    use common::metrics::apply_thresholds;
+    use common::types::{LabelScore, PredictionOptions, TokenStats};
+    use common::{
+        create_vocabulary_to_index_mapping, DefaultTokenizer, SpecialTokens, TokenizerConfig,
+        BOS_TOKEN, EOS_TOKEN,
+    };
+    use std::collections::HashMap;
+
+    fn make_index_to_class() -> HashMap<u32, String> {
+        HashMap::from([
+            (0, "politics".to_string()),
+            (1, "sports".to_string()),
+            (2, "weather".to_string()),
+        ])
+    }
+
+    const SCORES: [f32; 3] = [0.2, 0.9, 0.6];
+    const THRESHOLDS: [f32; 3] = [0.1, 0.5, 0.7];
+
+    fn predict(options: &PredictionOptions) -> Vec<LabelScore> {
+        let labels = score_labels(&SCORES, &make_index_to_class(), &THRESHOLDS, options);
+        select_labels(labels, options)
+    }
+
+    fn names(labels: &[LabelScore]) -> Vec<&str> {
+        labels.iter().map(|label| label.label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_validate_accepts_valid_options() {
+        assert!(PredictionOptions::default().validate().is_ok());
+
+        let options = PredictionOptions {
+            threshold: Some(0.0),
+            top_k: Some(1),
+            include_all_scores: true,
+        };
+        assert!(options.validate().is_ok());
+
+        let options = PredictionOptions {
+            threshold: Some(1.0),
+            ..Default::default()
+        };
+        assert!(options.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_threshold_out_of_range() {
+        for threshold in [-0.1, 1.1, f32::NAN] {
+            let options = PredictionOptions {
+                threshold: Some(threshold),
+                ..Default::default()
+            };
+            assert!(matches!(
+                options.validate(),
+                Err(ServingError::Validation(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_zero_top_k() {
+        let options = PredictionOptions {
+            top_k: Some(0),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            options.validate(),
+            Err(ServingError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_per_class_thresholds_by_default() {
+        let labels = predict(&PredictionOptions::default());
+
+        // Weather scores 0.6, under its threshold of 0.7, politics scores 0.2, over its 0.1
+        assert_eq!(vec!["sports", "politics"], names(&labels));
+    }
+
+    #[test]
+    fn test_threshold_overrides_the_per_class_thresholds() {
+        let options = PredictionOptions {
+            threshold: Some(0.4),
+            ..Default::default()
+        };
+
+        let labels = predict(&options);
+
+        assert_eq!(vec!["sports", "weather"], names(&labels));
+    }
+
+    #[test]
+    fn test_top_k_ignores_the_threshold() {
+        let options = PredictionOptions {
+            threshold: Some(0.95),
+            top_k: Some(2),
+            ..Default::default()
+        };
+
+        let labels = predict(&options);
+
+        assert_eq!(vec!["sports", "weather"], names(&labels));
+        assert!(labels.iter().all(|label| !label.above_threshold));
+    }
+
+    #[test]
+    fn test_top_k_larger_than_the_number_of_classes() {
+        let options = PredictionOptions {
+            top_k: Some(10),
+            ..Default::default()
+        };
+
+        assert_eq!(3, predict(&options).len());
+    }
+
+    #[test]
+    fn test_score_at_the_threshold_is_predicted_as_in_training() {
+        let scores = [0.5, 0.25];
+        let thresholds = [0.5, 0.3];
+
+        let labels = score_labels(
+            &scores,
+            &make_index_to_class(),
+            &thresholds,
+            &PredictionOptions::default(),
+        );
+
+        let flags: Vec<bool> = labels.iter().map(|label| label.above_threshold).collect();
+        assert_eq!(vec![true, false], flags);
+        assert_eq!(
+            vec![vec![1., 0.]],
+            apply_thresholds(&[scores.to_vec()], &thresholds)
+        );
+    }
+
+    #[test]
+    fn test_labels_are_sorted_by_descending_score() {
+        let options = PredictionOptions {
+            include_all_scores: true,
+            ..Default::default()
+        };
+
+        let labels = predict(&options);
+
+        assert_eq!(vec!["sports", "weather", "politics"], names(&labels));
+        assert!(labels.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    }
+
+    fn all_labels() -> Vec<LabelScore> {
+        score_labels(
+            &SCORES,
+            &make_index_to_class(),
+            &THRESHOLDS,
+            &PredictionOptions::default(),
+        )
+    }
+
+    #[test]
+    fn test_score_labels_flags_every_class() {
+        let labels = all_labels();
+
+        assert_eq!(
+            vec![
+                LabelScore {
+                    label: "sports".to_string(),
+                    score: 0.9,
+                    above_threshold: true,
+                },
+                LabelScore {
+                    label: "weather".to_string(),
+                    score: 0.6,
+                    above_threshold: false,
+                },
+                LabelScore {
+                    label: "politics".to_string(),
+                    score: 0.2,
+                    above_threshold: true,
+                },
+            ],
+            labels
+        );
+    }
+
+    #[test]
+    fn test_score_labels_skips_unknown_classes() {
+        let index_to_class = HashMap::from([(1, "sports".to_string())]);
+
+        let labels = score_labels(
+            &SCORES,
+            &index_to_class,
+            &THRESHOLDS,
+            &PredictionOptions::default(),
+        );
+
+        assert_eq!(vec!["sports"], names(&labels));
+    }
+
+    #[test]
+    fn test_select_labels_include_all_scores_wins_over_top_k() {
+        let options = PredictionOptions {
+            top_k: Some(1),
+            include_all_scores: true,
+            ..Default::default()
+        };
+
+        let labels = select_labels(all_labels(), &options);
+
+        assert_eq!(vec!["sports", "weather", "politics"], names(&labels));
+    }
+
+    fn v1_predictions(scores: &[f32], options: &PredictionOptions) -> Vec<HashMap<String, f32>> {
+        to_v1_response(scores, &make_index_to_class(), options).predictions
+    }
+
+    fn single(label: &str, score: f32) -> HashMap<String, f32> {
+        HashMap::from([(label.to_string(), score)])
+    }
+
+    #[test]
+    fn test_to_v1_response_uses_the_fixed_threshold_in_class_order() {
+        let response = to_v1_response(
+            &SCORES,
+            &make_index_to_class(),
+            &PredictionOptions::default(),
+        );
+
+        // The per-class thresholds are ignored: politics is left out at 0.2, weather kept at 0.6
+        assert_eq!(
+            vec![single("sports", 0.9), single("weather", 0.6)],
+            response.predictions
+        );
+        assert_eq!(None, response.scores);
+    }
+
+    #[test]
+    fn test_to_v1_response_keeps_the_scores_strictly_above_the_threshold() {
+        let predictions = v1_predictions(&[0.5, 0.7, 0.4], &PredictionOptions::default());
+
+        assert_eq!(vec![single("sports", 0.7)], predictions);
+    }
+
+    #[test]
+    fn test_to_v1_response_follows_the_class_indices_rather_than_the_scores() {
+        let predictions = v1_predictions(&[0.6, 0.9, 0.8], &PredictionOptions::default());
+
+        assert_eq!(
+            vec![
+                single("politics", 0.6),
+                single("sports", 0.9),
+                single("weather", 0.8)
+            ],
+            predictions
+        );
+    }
+
+    #[test]
+    fn test_to_v1_response_with_a_threshold() {
+        let options = PredictionOptions {
+            threshold: Some(0.1),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            vec![
+                single("politics", 0.2),
+                single("sports", 0.9),
+                single("weather", 0.6)
+            ],
+            v1_predictions(&SCORES, &options)
+        );
+    }
+
+    #[test]
+    fn test_to_v1_response_with_top_k_and_all_scores() {
+        let options = PredictionOptions {
+            top_k: Some(2),
+            include_all_scores: true,
+            ..Default::default()
+        };
+
+        let response = to_v1_response(&[0.7, 0.2, 0.9], &make_index_to_class(), &options);
+
+        // The two highest scores, still in the order of the classes
+        assert_eq!(
+            vec![single("politics", 0.7), single("weather", 0.9)],
+            response.predictions
+        );
+        assert_eq!(
+            Some(HashMap::from([
+                ("politics".to_string(), 0.7),
+                ("sports".to_string(), 0.2),
+                ("weather".to_string(), 0.9),
+            ])),
+            response.scores
+        );
+    }
+
+    fn make_word_to_index(special_tokens: &SpecialTokens) -> HashMap<String, u32> {
+        let vocabulary = vec!["storm".to_string(), "coast".to_string()];
+        create_vocabulary_to_index_mapping(&vocabulary, special_tokens)
+    }
+
+    #[test]
+    fn test_token_stats_counts_the_unknown_tokens() {
+        let tokenizer = DefaultTokenizer::new(TokenizerConfig::default());
+        let special_tokens = SpecialTokens::default();
+        let word_to_index = make_word_to_index(&special_tokens);
+
+        let stats = token_stats(
+            "Storm hits the coast",
+            &tokenizer,
+            &word_to_index,
+            &special_tokens,
+            8,
+        );
+
+        assert_eq!(
+            TokenStats {
+                n_tokens: 4,
+                n_unknown_tokens: 2,
+                truncated: false,
+            },
+            stats
+        );
+    }
+
+    #[test]
+    fn test_token_stats_only_counts_the_tokens_kept() {
+        let tokenizer = DefaultTokenizer::new(TokenizerConfig::default());
+        let special_tokens = SpecialTokens {
+            bos: Some(BOS_TOKEN.to_string()),
+            eos: Some(EOS_TOKEN.to_string()),
+            ..SpecialTokens::default()
+        };
+        let word_to_index = make_word_to_index(&special_tokens);
+
+        // The markers take 2 of the 4 positions, "the coast" is dropped
+        let stats = token_stats(
+            "storm hits the coast",
+            &tokenizer,
+            &word_to_index,
+            &special_tokens,
+            4,
+        );
+
+        assert_eq!(
+            TokenStats {
+                n_tokens: 2,
+                n_unknown_tokens: 1,
+                truncated: true,
+            },
+            stats
+        );
+    }
+}
@@ -0,0 +1,141 @@
+mod support;
+
+#[cfg(test)]
+mod test_input {
+
+    use super::support::temp_dir;
+    use common::input::{Record, RecordReader};
+    use common::prediction_config::InputFormat;
+    use std::fs;
+
+    fn write_file(test_name: &str, file_name: &str, content: &str) -> String {
+        let path = format!("{}/{}", temp_dir(test_name), file_name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn texts(records: &[Record]) -> Vec<&str> {
+        records.iter().map(|record| record.text.as_str()).collect()
+    }
+
+    fn ids(records: &[Record]) -> Vec<Option<&str>> {
+        records.iter().map(|record| record.id.as_deref()).collect()
+    }
+
+    #[test]
+    fn test_csv_reader_reads_batches() {
+        let path = write_file(
+            "test_csv_reader_reads_batches",
+            "texts.csv",
+            "id,text\n1,Storm hits the coast\n2,\"Local team, again\"\n3,Heavy rain\n",
+        );
+        let mut reader = RecordReader::open(&path, InputFormat::Csv, "text", Some("id")).unwrap();
+
+        let first = reader.next_batch(2).unwrap();
+        let second = reader.next_batch(2).unwrap();
+
+        assert_eq!(
+            vec!["Storm hits the coast", "Local team, again"],
+            texts(&first)
+        );
+        assert_eq!(vec![Some("1"), Some("2")], ids(&first));
+        assert_eq!(vec!["Heavy rain"], texts(&second));
+        assert!(reader.next_batch(2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_csv_reader_without_id_column() {
+        let path = write_file(
+            "test_csv_reader_without_id_column",
+            "texts.csv",
+            "id,text\n1,Storm\n",
+        );
+        let mut reader = RecordReader::open(&path, InputFormat::Csv, "text", None).unwrap();
+
+        assert_eq!(vec![None], ids(&reader.next_batch(10).unwrap()));
+    }
+
+    #[test]
+    fn test_csv_reader_rejects_a_missing_column() {
+        let path = write_file(
+            "test_csv_reader_rejects_a_missing_column",
+            "texts.csv",
+            "id,headline\n1,Storm\n",
+        );
+
+        let error = RecordReader::open(&path, InputFormat::Csv, "text", None)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("text column"));
+        assert!(RecordReader::open(&path, InputFormat::Csv, "headline", Some("key")).is_err());
+    }
+
+    #[test]
+    fn test_jsonl_reader_skips_blank_lines() {
+        let path = write_file(
+            "test_jsonl_reader_skips_blank_lines",
+            "texts.jsonl",
+            "{\"text\": \"Storm\"}\n\n  \n{\"text\": \"Rain\"}\n\n",
+        );
+        let mut reader = RecordReader::open(&path, InputFormat::Jsonl, "text", None).unwrap();
+
+        assert_eq!(
+            vec!["Storm", "Rain"],
+            texts(&reader.next_batch(10).unwrap())
+        );
+        assert!(reader.next_batch(10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_jsonl_reader_converts_non_string_ids() {
+        let path = write_file(
+            "test_jsonl_reader_converts_non_string_ids",
+            "texts.jsonl",
+            concat!(
+                "{\"id\": \"a\", \"text\": \"Storm\"}\n",
+                "{\"id\": 7, \"text\": \"Rain\"}\n",
+                "{\"id\": null, \"text\": \"Wind\"}\n",
+                "{\"text\": \"Snow\"}\n",
+            ),
+        );
+        let mut reader = RecordReader::open(&path, InputFormat::Jsonl, "text", Some("id")).unwrap();
+
+        let records = reader.next_batch(10).unwrap();
+
+        assert_eq!(vec![Some("a"), Some("7"), None, None], ids(&records));
+    }
+
+    #[test]
+    fn test_jsonl_reader_rejects_a_line_without_text() {
+        let path = write_file(
+            "test_jsonl_reader_rejects_a_line_without_text",
+            "texts.jsonl",
+            "{\"text\": \"Storm\"}\n\n{\"text\": 3}\n",
+        );
+        let mut reader = RecordReader::open(&path, InputFormat::Jsonl, "text", None).unwrap();
+
+        let error = reader.next_batch(10).unwrap_err();
+
+        // Blank lines count in the line numbers
+        assert!(error.to_string().contains("Line 3"));
+    }
+
+    #[test]
+    fn test_jsonl_reader_rejects_invalid_json() {
+        let path = write_file(
+            "test_jsonl_reader_rejects_invalid_json",
+            "texts.jsonl",
+            "{\"text\": \"Storm\"\n",
+        );
+        let mut reader = RecordReader::open(&path, InputFormat::Jsonl, "text", None).unwrap();
+
+        assert!(reader.next_batch(10).is_err());
+    }
+
+    #[test]
+    fn test_reader_rejects_a_missing_file() {
+        for format in [InputFormat::Csv, InputFormat::Jsonl] {
+            assert!(RecordReader::open("does/not/exist", format, "text", None).is_err());
+        }
+    }
+}
@@ -0,0 +1,50 @@
+mod support;
+
+#[cfg(test)]
+mod test_loader {
+
+    use super::support::{temp_dir, write_artifacts};
+    use common::loader::load_shared_data;
+    use common::ArtifactPaths;
+
+    #[test]
+    fn test_artifact_paths_in_directory_keep_the_default_file_names() {
+        let paths = ArtifactPaths::in_directory("models/sections/2");
+
+        assert_eq!(paths.model, "models/sections/2/model.bin");
+        assert_eq!(paths.model_config, "models/sections/2/model_config.json");
+        assert_eq!(paths.vocabulary, "models/sections/2/vocab.json");
+        assert_eq!(
+            paths.index_to_class,
+            "models/sections/2/index_to_class.json"
+        );
+    }
+
+    #[test]
+    fn test_default_artifact_paths_are_in_the_model_directory() {
+        let paths = ArtifactPaths::default();
+        let in_directory = ArtifactPaths::in_directory("model");
+
+        assert_eq!(in_directory.model, paths.model);
+        assert_eq!(in_directory.model_config, paths.model_config);
+        assert_eq!(in_directory.vocabulary, paths.vocabulary);
+        assert_eq!(in_directory.index_to_class, paths.index_to_class);
+    }
+
+    #[test]
+    fn test_load_shared_data_loads_the_artifacts_of_a_training_run() {
+        let directory = temp_dir("test_load_shared_data_loads_the_artifacts_of_a_training_run");
+        let paths = write_artifacts(&directory, &["sports", "weather"]);
+
+        let data = load_shared_data(&paths).unwrap();
+
+        assert_eq!(2, data.index_to_class.len());
+        assert_eq!(data.thresholds.len(), data.index_to_class.len());
+        assert_eq!(data.max_seq_len, data.bundle.model.max_seq_len);
+    }
+
+    #[test]
+    fn test_load_shared_data_fails_on_missing_artifacts() {
+        assert!(load_shared_data(&ArtifactPaths::in_directory("does/not/exist")).is_err());
+    }
+}
@@ -1,7 +1,6 @@
 #[cfg(test)]
 mod test_metrics {
 
-    use candle_core::{Device, Tensor};
     use common::metrics::*;
 
     #[test]
@@ -36,4 +35,51 @@ mod test_metrics {
 
         assert_eq!(expected_result, actual_result);
     }
+
+    #[test]
+    fn test_f_beta_score() {
+        assert_eq!(0., f_beta_score(0, 3, 2, 1.));
+        // Precision 2/3, recall 1/2
+        assert!((f_beta_score(2, 1, 2, 1.) - 4. / 7.).abs() < 1e-6);
+        // A small beta moves the score towards the precision, a large one towards the recall
+        assert!(f_beta_score(2, 1, 2, 0.5) > f_beta_score(2, 1, 2, 1.));
+        assert!(f_beta_score(2, 1, 2, 2.) < f_beta_score(2, 1, 2, 1.));
+    }
+
+    #[test]
+    fn test_calibrate_thresholds() {
+        // The second class is rare and always scored low, but still above its negatives
+        let scores = vec![
+            vec![0.9, 0.3],
+            vec![0.8, 0.1],
+            vec![0.2, 0.35],
+            vec![0.1, 0.05],
+        ];
+        let actual = vec![vec![1., 1.], vec![1., 0.], vec![0., 1.], vec![0., 0.]];
+        let candidates = vec![0.25, 0.5, 0.75];
+
+        let thresholds = calibrate_thresholds(&scores, &actual, &candidates, 1., 0.5);
+
+        // Both 0.25 and 0.5 separate the first class, 0.5 is kept as the closest to the default
+        assert_eq!(vec![0.5, 0.25], thresholds);
+    }
+
+    #[test]
+    fn test_calibrate_thresholds_without_positives_keeps_default() {
+        let scores = vec![vec![0.9], vec![0.1]];
+        let actual = vec![vec![0.], vec![0.]];
+
+        let thresholds = calibrate_thresholds(&scores, &actual, &[0.25, 0.75], 1., 0.5);
+
+        assert_eq!(vec![0.5], thresholds);
+    }
+
+    #[test]
+    fn test_apply_thresholds() {
+        let scores = vec![vec![0.4, 0.4], vec![0.6, 0.2]];
+
+        let predicted = apply_thresholds(&scores, &[0.5, 0.3]);
+
+        assert_eq!(vec![vec![0., 1.], vec![1., 0.]], predicted);
+    }
 }
@@ -0,0 +1,503 @@
+#[cfg(test)]
+mod test_model {
+
+    use candle_core::{DType, Device, Tensor};
+    use candle_nn::{VarBuilder, VarMap};
+    use common::*;
+
+    const MAX_SEQ_LEN: usize = 8;
+
+    fn make_config(architecture: Architecture) -> ModelConfig {
+        ModelConfig {
+            architecture,
+            vocab_size: 32,
+            embedding_size: 4,
+            hidden_size: 6,
+            n_classes: 3,
+            max_seq_len: MAX_SEQ_LEN,
+            kernel_sizes: vec![2, 3],
+            n_filters: 5,
+            n_heads: 2,
+            ..ModelConfig::default()
+        }
+    }
+
+    fn make_model_with(architecture: Architecture) -> Box<dyn TextClassifier> {
+        let varmap = VarMap::new();
+        let vs = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        build_classifier(&vs, &make_config(architecture)).unwrap()
+    }
+
+    fn make_model() -> Box<dyn TextClassifier> {
+        make_model_with(Architecture::MeanPooling)
+    }
+
+    fn forward(model: &dyn TextClassifier, input: &Tensor) -> candle_core::Result<Tensor> {
+        model.forward(input, &padding_mask(input)?)
+    }
+
+    fn forward_t(
+        model: &dyn TextClassifier,
+        input: &Tensor,
+        train: bool,
+    ) -> candle_core::Result<Tensor> {
+        model.forward_t(input, &padding_mask(input)?, train)
+    }
+
+    fn assert_close(a: &[f32], b: &[f32]) {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+        }
+    }
+
+    fn encode(texts: &[String]) -> Tensor {
+        let corpus = vec![
+            "blizzard hits the south".to_string(),
+            "team wins the final".to_string(),
+        ];
+        let tokenizer = DefaultTokenizer::default();
+        let vocabulary = VocabularyBuilder::new().build(&corpus, &tokenizer);
+        let mapping =
+            create_vocabulary_to_index_mapping(&vocabulary.vocabulary, &vocabulary.special_tokens);
+        encode_texts(
+            texts,
+            &tokenizer,
+            &mapping,
+            &vocabulary.special_tokens,
+            MAX_SEQ_LEN,
+            &Device::Cpu,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_encode_texts_shape() {
+        let texts = vec!["Blizzard hits!".to_string(), "Team wins".to_string()];
+
+        let tensor = encode(&texts);
+
+        assert_eq!((2, MAX_SEQ_LEN), tensor.dims2().unwrap());
+    }
+
+    #[test]
+    fn test_batch_and_single_encodings_yield_identical_logits() {
+        let model = make_model();
+        let texts = vec![
+            "Blizzard hits the South!".to_string(),
+            "Team wins the final, again".to_string(),
+            "Unknown words only".to_string(),
+        ];
+
+        // Training encodes the whole dataset at once, inference encodes one headline at a time.
+        let batch_logits = forward(&*model, &encode(&texts)).unwrap();
+        assert_eq!((3, 3), batch_logits.dims2().unwrap());
+        let batch_logits = batch_logits.to_vec2::<f32>().unwrap();
+
+        for (text, expected_logits) in texts.iter().zip(batch_logits) {
+            let single_logits = forward(&*model, &encode(std::slice::from_ref(text))).unwrap();
+            let single_logits = single_logits.squeeze(0).unwrap().to_vec1::<f32>().unwrap();
+            for (single, batch) in single_logits.iter().zip(expected_logits.iter()) {
+                assert!((single - batch).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_padding_does_not_change_logits() {
+        let model = make_model();
+        let short = Tensor::new(&[[2u32, 3]], &Device::Cpu).unwrap();
+        let padded = Tensor::new(&[[2u32, 3, PAD_INDEX, PAD_INDEX]], &Device::Cpu).unwrap();
+
+        let short_logits = forward(&*model, &short).unwrap().to_vec2::<f32>().unwrap();
+        let padded_logits = forward(&*model, &padded).unwrap().to_vec2::<f32>().unwrap();
+
+        for (s, p) in short_logits[0].iter().zip(padded_logits[0].iter()) {
+            assert!((s - p).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_forward_rejects_unbatched_input() {
+        let model = make_model();
+        let unbatched = Tensor::new(&[2u32, 3, 4], &Device::Cpu).unwrap();
+
+        let error = forward(&*model, &unbatched).unwrap_err();
+
+        assert!(error.to_string().contains("(batch, seq_len)"));
+    }
+
+    #[test]
+    fn test_cnn_padding_does_not_change_logits() {
+        let model = make_model_with(Architecture::Cnn);
+        // Shorter than the widest kernel, so that the windows run over the padding
+        let short = Tensor::new(&[[2u32, 3]], &Device::Cpu).unwrap();
+        let padded = Tensor::new(&[[2u32, 3, PAD_INDEX, PAD_INDEX]], &Device::Cpu).unwrap();
+
+        let short_logits = forward(&*model, &short).unwrap().to_vec2::<f32>().unwrap();
+        let padded_logits = forward(&*model, &padded).unwrap().to_vec2::<f32>().unwrap();
+
+        assert_eq!(3, short_logits[0].len());
+        assert_close(&short_logits[0], &padded_logits[0]);
+    }
+
+    #[test]
+    fn test_cnn_batch_and_single_encodings_yield_identical_logits() {
+        let model = make_model_with(Architecture::Cnn);
+        let texts = vec![
+            "Blizzard hits the South!".to_string(),
+            "Team wins".to_string(),
+        ];
+
+        let batch_logits = forward(&*model, &encode(&texts)).unwrap();
+        let batch_logits = batch_logits.to_vec2::<f32>().unwrap();
+
+        for (text, expected_logits) in texts.iter().zip(batch_logits) {
+            let single_logits = forward(&*model, &encode(std::slice::from_ref(text))).unwrap();
+            let single_logits = single_logits.squeeze(0).unwrap().to_vec1::<f32>().unwrap();
+            assert_close(&single_logits, &expected_logits);
+        }
+    }
+
+    #[test]
+    fn test_mean_pooling_has_no_activation_between_the_linear_layers() {
+        let mut varmap = VarMap::new();
+        let vs = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        let config = make_config(Architecture::MeanPooling);
+        let model = build_classifier(&vs, &config).unwrap();
+
+        // A hidden layer with only negative outputs, which reach the classifier unchanged so
+        // that the models trained before keep their scores
+        let weight = Tensor::zeros(
+            (config.hidden_size, config.embedding_size),
+            DType::F32,
+            &Device::Cpu,
+        )
+        .unwrap();
+        let bias = Tensor::full(-1f32, config.hidden_size, &Device::Cpu).unwrap();
+        varmap.set_one("linear.weight", &weight).unwrap();
+        varmap.set_one("linear.bias", &bias).unwrap();
+
+        let logits = forward(&*model, &encode(&["team wins".to_string()])).unwrap();
+        let data = varmap.data().lock().unwrap();
+        let classifier_weight = data["classifier.weight"]
+            .as_tensor()
+            .to_vec2::<f32>()
+            .unwrap();
+        let classifier_bias = data["classifier.bias"]
+            .as_tensor()
+            .to_vec1::<f32>()
+            .unwrap();
+        let expected: Vec<f32> = classifier_weight
+            .iter()
+            .zip(&classifier_bias)
+            .map(|(row, bias)| bias - row.iter().sum::<f32>())
+            .collect();
+
+        assert_close(&expected, &logits.to_vec2::<f32>().unwrap()[0]);
+    }
+
+    #[test]
+    fn test_cnn_dropout_is_only_applied_in_training() {
+        let model = make_model_with(Architecture::Cnn);
+        let input = encode(&["Blizzard hits the South!".to_string()]);
+
+        let first = forward(&*model, &input).unwrap().to_vec2::<f32>().unwrap();
+        let second = forward_t(&*model, &input, false)
+            .unwrap()
+            .to_vec2::<f32>()
+            .unwrap();
+        assert_close(&first[0], &second[0]);
+
+        // Dropout zeroes some features and scales up the others, so that every training pass
+        // changes the logits
+        for _ in 0..5 {
+            let trained = forward_t(&*model, &input, true).unwrap();
+            assert_eq!((1, 3), trained.dims2().unwrap());
+            let trained = trained.to_vec2::<f32>().unwrap();
+            let max_difference = trained[0]
+                .iter()
+                .zip(&first[0])
+                .map(|(a, b)| (a - b).abs())
+                .fold(0., f32::max);
+            assert!(max_difference > 1e-5, "{:?} == {:?}", trained[0], first[0]);
+        }
+    }
+
+    #[test]
+    fn test_cnn_without_dropout_trains_on_the_features() {
+        let varmap = VarMap::new();
+        let vs = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        let config = ModelConfig {
+            dropout: 0.,
+            ..make_config(Architecture::Cnn)
+        };
+        let model = build_classifier(&vs, &config).unwrap();
+        let input = encode(&["Blizzard hits the South!".to_string()]);
+
+        let evaluated = forward(&*model, &input).unwrap().to_vec2::<f32>().unwrap();
+        let trained = forward_t(&*model, &input, true)
+            .unwrap()
+            .to_vec2::<f32>()
+            .unwrap();
+
+        assert_close(&evaluated[0], &trained[0]);
+    }
+
+    #[test]
+    fn test_cnn_rejects_invalid_kernel_sizes() {
+        let varmap = VarMap::new();
+        let vs = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        let config = ModelConfig {
+            kernel_sizes: vec![],
+            ..make_config(Architecture::Cnn)
+        };
+
+        let error = build_classifier(&vs, &config).unwrap_err();
+
+        assert!(error.to_string().contains("kernel sizes"));
+    }
+
+    #[test]
+    fn test_model_config_without_architecture_settings_uses_defaults() {
+        let config: ModelConfig =
+            serde_json::from_str(r#"{"vocab_size": 10, "n_classes": 2}"#).unwrap();
+
+        assert_eq!(Architecture::MeanPooling, config.architecture);
+        assert_eq!(vec![2, 3, 4], config.kernel_sizes);
+    }
+
+    fn make_pooled_model(architecture: Architecture, pooling: Pooling) -> Box<dyn TextClassifier> {
+        let varmap = VarMap::new();
+        let vs = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        let config = ModelConfig {
+            pooling,
+            ..make_config(architecture)
+        };
+        build_classifier(&vs, &config).unwrap()
+    }
+
+    /// Check that every row of a padded batch gets the logits of the row without padding.
+    fn assert_padding_does_not_change_logits(model: &dyn TextClassifier) {
+        // Rows of different lengths, so that the padding of the batch differs from the one of
+        // every row
+        let batch = Tensor::new(
+            &[
+                [2u32, 3, 4, PAD_INDEX, PAD_INDEX],
+                [5, 6, PAD_INDEX, PAD_INDEX, PAD_INDEX],
+            ],
+            &Device::Cpu,
+        )
+        .unwrap();
+        let rows = [
+            Tensor::new(&[[2u32, 3, 4]], &Device::Cpu).unwrap(),
+            Tensor::new(&[[5u32, 6]], &Device::Cpu).unwrap(),
+        ];
+
+        let batch_logits = forward(model, &batch).unwrap().to_vec2::<f32>().unwrap();
+        for (row, expected_logits) in rows.iter().zip(batch_logits) {
+            let row_logits = forward(model, row).unwrap().to_vec2::<f32>().unwrap();
+            assert_close(&row_logits[0], &expected_logits);
+        }
+    }
+
+    #[test]
+    fn test_recurrent_padding_does_not_change_logits() {
+        for architecture in [Architecture::Lstm, Architecture::Gru] {
+            for pooling in [Pooling::Last, Pooling::Max, Pooling::Mean] {
+                assert_padding_does_not_change_logits(&*make_pooled_model(architecture, pooling));
+            }
+        }
+    }
+
+    #[test]
+    fn test_recurrent_logits_depend_on_word_order() {
+        let model = make_pooled_model(Architecture::Lstm, Pooling::Last);
+        let mean_pooling = make_model();
+        let ordered = Tensor::new(&[[2u32, 3, 4]], &Device::Cpu).unwrap();
+        let shuffled = Tensor::new(&[[4u32, 3, 2]], &Device::Cpu).unwrap();
+
+        let logits = |model: &dyn TextClassifier, input: &Tensor| {
+            forward(model, input)
+                .unwrap()
+                .to_vec2::<f32>()
+                .unwrap()
+                .remove(0)
+        };
+
+        assert_close(
+            &logits(&*mean_pooling, &ordered),
+            &logits(&*mean_pooling, &shuffled),
+        );
+        let (ordered, shuffled) = (logits(&*model, &ordered), logits(&*model, &shuffled));
+        assert!(ordered
+            .iter()
+            .zip(shuffled.iter())
+            .any(|(a, b)| (a - b).abs() > 1e-6));
+    }
+
+    #[test]
+    fn test_recurrent_model_trains_with_dropout() {
+        let model = make_pooled_model(Architecture::Gru, Pooling::Max);
+        let input = encode(&["Blizzard hits the South!".to_string()]);
+
+        let logits = forward_t(&*model, &input, true).unwrap();
+
+        assert_eq!((1, 3), logits.dims2().unwrap());
+    }
+
+    #[test]
+    fn test_transformer_padding_does_not_change_logits() {
+        for pooling in [Pooling::Cls, Pooling::Max, Pooling::Mean] {
+            assert_padding_does_not_change_logits(&*make_pooled_model(
+                Architecture::Transformer,
+                pooling,
+            ));
+        }
+    }
+
+    #[test]
+    fn test_transformer_logits_depend_on_word_order() {
+        let model = make_pooled_model(Architecture::Transformer, Pooling::Mean);
+        let ordered = Tensor::new(&[[2u32, 3, 4]], &Device::Cpu).unwrap();
+        let shuffled = Tensor::new(&[[4u32, 3, 2]], &Device::Cpu).unwrap();
+
+        let ordered = forward(&*model, &ordered)
+            .unwrap()
+            .to_vec2::<f32>()
+            .unwrap();
+        let shuffled = forward(&*model, &shuffled)
+            .unwrap()
+            .to_vec2::<f32>()
+            .unwrap();
+
+        assert!(ordered[0]
+            .iter()
+            .zip(shuffled[0].iter())
+            .any(|(a, b)| (a - b).abs() > 1e-6));
+    }
+
+    #[test]
+    fn test_transformer_handles_texts_of_the_maximum_length() {
+        let model = make_pooled_model(Architecture::Transformer, Pooling::Cls);
+        let input = Tensor::new(&[[2u32; MAX_SEQ_LEN]], &Device::Cpu).unwrap();
+
+        let logits = forward_t(&*model, &input, true).unwrap();
+
+        assert_eq!((1, 3), logits.dims2().unwrap());
+    }
+
+    #[test]
+    fn test_transformer_rejects_invalid_settings() {
+        let varmap = VarMap::new();
+        let vs = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        let heads_not_dividing = ModelConfig {
+            n_heads: 3,
+            ..make_config(Architecture::Transformer)
+        };
+        let last_pooling = ModelConfig {
+            pooling: Pooling::Last,
+            ..make_config(Architecture::Transformer)
+        };
+
+        let error = build_classifier(&vs, &heads_not_dividing).unwrap_err();
+        assert!(error.to_string().contains("heads"));
+        assert!(build_classifier(&vs, &last_pooling).is_err());
+    }
+
+    #[test]
+    fn test_n_parameters_counts_every_variable() {
+        // The only combinations of an architecture and a pooling that cannot be built
+        let unsupported = [
+            (Architecture::Lstm, Pooling::Cls),
+            (Architecture::Gru, Pooling::Cls),
+            (Architecture::Transformer, Pooling::Last),
+        ];
+
+        for architecture in [
+            Architecture::MeanPooling,
+            Architecture::Cnn,
+            Architecture::Lstm,
+            Architecture::Gru,
+            Architecture::Transformer,
+        ] {
+            for pooling in [Pooling::Last, Pooling::Max, Pooling::Mean, Pooling::Cls] {
+                let varmap = VarMap::new();
+                let vs = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+                let config = ModelConfig {
+                    pooling,
+                    ..make_config(architecture)
+                };
+                let result = build_classifier(&vs, &config);
+
+                if unsupported.contains(&(architecture, pooling)) {
+                    assert!(result.is_err(), "{:?} {:?}", architecture, pooling);
+                    continue;
+                }
+                let model = result.unwrap();
+
+                let n_variables: usize = varmap.all_vars().iter().map(|var| var.elem_count()).sum();
+                assert_eq!(
+                    n_variables,
+                    model.n_parameters(),
+                    "{:?} {:?}",
+                    architecture,
+                    pooling
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_classifier_is_rebuilt_from_its_stored_config() {
+        let varmap = VarMap::new();
+        let vs = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        let model = build_classifier(&vs, &make_config(Architecture::Gru)).unwrap();
+        let path =
+            std::env::temp_dir().join("test_classifier_is_rebuilt_from_its_stored_config.bin");
+        let path = path.to_str().unwrap();
+        varmap.save(path).unwrap();
+
+        let stored = serde_json::to_string(model.config()).unwrap();
+        let config: ModelConfig = serde_json::from_str(&stored).unwrap();
+        let loaded = load_model(path, &config).unwrap();
+
+        assert_eq!(Architecture::Gru, loaded.config().architecture);
+        assert_eq!(model.n_parameters(), loaded.n_parameters());
+        let input = encode(&["Blizzard hits the South!".to_string()]);
+        assert_close(
+            &forward(&*model, &input).unwrap().to_vec2::<f32>().unwrap()[0],
+            &forward(&*loaded, &input).unwrap().to_vec2::<f32>().unwrap()[0],
+        );
+    }
+
+    #[test]
+    fn test_forward_rejects_mask_of_another_shape() {
+        let model = make_model_with(Architecture::Cnn);
+        let input = Tensor::new(&[[2u32, 3, 4]], &Device::Cpu).unwrap();
+        let mask = Tensor::new(&[[1f32, 1.]], &Device::Cpu).unwrap();
+
+        let error = model.forward(&input, &mask).unwrap_err();
+
+        assert!(error.to_string().contains("mask"));
+    }
+
+    #[test]
+    fn test_every_architecture_names_its_embedding_table() {
+        for architecture in [
+            Architecture::MeanPooling,
+            Architecture::Cnn,
+            Architecture::Lstm,
+            Architecture::Gru,
+            Architecture::Transformer,
+        ] {
+            let varmap = VarMap::new();
+            let vs = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+            build_classifier(&vs, &make_config(architecture)).unwrap();
+
+            let variables = varmap.data().lock().unwrap();
+            let table = variables.get(EMBEDDING_VARIABLE).unwrap();
+            assert_eq!((32, 4), table.dims2().unwrap(), "{:?}", architecture);
+        }
+    }
+}
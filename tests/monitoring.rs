@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod test_monitoring {
+
+    use common::monitoring::ServingMetrics;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render_reports_requests_by_route_and_status() {
+        let metrics = ServingMetrics::new().unwrap();
+
+        metrics.observe_request("/predict", 200, Duration::from_millis(3));
+        metrics.observe_request("/predict", 200, Duration::from_millis(5));
+        metrics.observe_request("/predict/batch", 413, Duration::from_millis(1));
+
+        let rendered = metrics.render().unwrap();
+
+        assert!(
+            rendered.contains(r#"inference_http_requests_total{route="/predict",status="200"} 2"#)
+        );
+        assert!(rendered
+            .contains(r#"inference_http_requests_total{route="/predict/batch",status="413"} 1"#));
+        assert!(
+            rendered.contains(r#"inference_request_duration_seconds_count{route="/predict"} 2"#)
+        );
+    }
+
+    #[test]
+    fn test_unknown_paths_share_a_route_label() {
+        let metrics = ServingMetrics::new().unwrap();
+
+        metrics.observe_request("/wp-admin", 404, Duration::from_millis(1));
+        metrics.observe_request("/.env", 404, Duration::from_millis(1));
+
+        let rendered = metrics.render().unwrap();
+
+        assert!(rendered.contains(r#"inference_http_requests_total{route="other",status="404"} 2"#));
+        assert!(!rendered.contains("wp-admin"));
+    }
+
+    #[test]
+    fn test_model_routes_are_reported_with_placeholders() {
+        let metrics = ServingMetrics::new().unwrap();
+
+        metrics.observe_request("/models", 200, Duration::from_millis(1));
+        metrics.observe_request("/models/sections/predict", 200, Duration::from_millis(1));
+        metrics.observe_request("/models/sentiment/predict", 200, Duration::from_millis(1));
+        metrics.observe_request(
+            "/models/sections/versions/2/predict",
+            404,
+            Duration::from_millis(1),
+        );
+
+        let rendered = metrics.render().unwrap();
+
+        assert!(
+            rendered.contains(r#"inference_http_requests_total{route="/models",status="200"} 1"#)
+        );
+        assert!(rendered.contains(
+            r#"inference_http_requests_total{route="/models/{name}/predict",status="200"} 2"#
+        ));
+        assert!(rendered.contains(
+            r#"inference_http_requests_total{route="/models/{name}/versions/{version}/predict",status="404"} 1"#
+        ));
+        assert!(!rendered.contains("sentiment"));
+    }
+
+    #[test]
+    fn test_render_reports_predictions_by_class() {
+        let metrics = ServingMetrics::new().unwrap();
+        metrics.observe_predictions("default", ["sports", "weather"]);
+        metrics.observe_predictions("default", ["weather"]);
+        metrics.observe_predictions("sections/2", ["weather"]);
+
+        let rendered = metrics.render().unwrap();
+
+        assert!(
+            rendered.contains(r#"inference_predictions_total{class="sports",model="default"} 1"#)
+        );
+        assert!(
+            rendered.contains(r#"inference_predictions_total{class="weather",model="default"} 2"#)
+        );
+        assert!(rendered
+            .contains(r#"inference_predictions_total{class="weather",model="sections/2"} 1"#));
+    }
+
+    #[test]
+    fn test_render_reports_histograms() {
+        let metrics = ServingMetrics::new().unwrap();
+
+        metrics.observe_batch_size(4);
+        metrics.observe_unk_token_ratio(0.25);
+        metrics.observe_tokenization(Duration::from_micros(50));
+        metrics.observe_forward(Duration::from_millis(2));
+
+        let rendered = metrics.render().unwrap();
+
+        assert!(rendered.contains("inference_batch_size_sum 4"));
+        assert!(rendered.contains("inference_unk_token_ratio_sum 0.25"));
+        assert!(rendered.contains("inference_tokenization_duration_seconds_count 1"));
+        assert!(rendered.contains("inference_forward_duration_seconds_count 1"));
+    }
+
+    #[test]
+    fn test_render_reports_reloads_by_result() {
+        let metrics = ServingMetrics::new().unwrap();
+
+        metrics.observe_reload(true);
+        metrics.observe_reload(false);
+        metrics.observe_reload(true);
+
+        let rendered = metrics.render().unwrap();
+
+        assert!(rendered.contains(r#"inference_reloads_total{result="success"} 2"#));
+        assert!(rendered.contains(r#"inference_reloads_total{result="failure"} 1"#));
+    }
+}
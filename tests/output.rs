@@ -0,0 +1,149 @@
+mod support;
+
+#[cfg(test)]
+mod test_output {
+
+    use super::support::temp_dir;
+    use common::output::{Prediction, PredictionWriter};
+    use common::prediction_config::OutputFormat;
+    use polars::prelude::{ParquetReader, SerReader};
+    use serde_json::{json, Value};
+    use std::fs::{self, File};
+
+    fn class_names() -> Vec<String> {
+        vec!["sports".to_string(), "weather".to_string()]
+    }
+
+    /// A classified text, then a text that could not be classified.
+    fn make_predictions() -> Vec<Prediction> {
+        vec![
+            Prediction {
+                id: Some("1".to_string()),
+                labels: vec!["weather".to_string(), "sports".to_string()],
+                scores: Some(vec![0.75, 0.5]),
+            },
+            Prediction {
+                id: Some("2".to_string()),
+                labels: Vec::new(),
+                scores: None,
+            },
+        ]
+    }
+
+    fn write(
+        test_name: &str,
+        file_name: &str,
+        format: OutputFormat,
+        id_column: Option<&str>,
+    ) -> String {
+        let path = format!("{}/{}", temp_dir(test_name), file_name);
+        let mut writer = PredictionWriter::create(&path, format, id_column, class_names()).unwrap();
+        let predictions = make_predictions();
+        // Written in two batches, as the predict binary does
+        writer.write_batch(&predictions[..1]).unwrap();
+        writer.write_batch(&predictions[1..]).unwrap();
+        writer.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_csv_has_a_score_column_per_class() {
+        let path = write(
+            "test_csv_has_a_score_column_per_class",
+            "predictions.csv",
+            OutputFormat::Csv,
+            Some("key"),
+        );
+
+        assert_eq!(
+            "key,labels,score_sports,score_weather\n1,weather|sports,0.75,0.5\n2,,,\n",
+            fs::read_to_string(path).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_csv_without_id_column() {
+        let path = write(
+            "test_csv_without_id_column",
+            "predictions.csv",
+            OutputFormat::Csv,
+            None,
+        );
+
+        let content = fs::read_to_string(path).unwrap();
+        assert_eq!(
+            Some("labels,score_sports,score_weather"),
+            content.lines().next()
+        );
+    }
+
+    #[test]
+    fn test_jsonl_keys_the_scores_by_class() {
+        let path = write(
+            "test_jsonl_keys_the_scores_by_class",
+            "predictions.jsonl",
+            OutputFormat::Jsonl,
+            Some("key"),
+        );
+
+        let lines: Vec<Value> = fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            vec![
+                json!({"key": "1", "labels": ["weather", "sports"], "scores": {"sports": 0.75, "weather": 0.5}}),
+                json!({"key": "2", "labels": [], "scores": null}),
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn test_parquet_has_a_score_column_per_class() {
+        let path = write(
+            "test_parquet_has_a_score_column_per_class",
+            "predictions.parquet",
+            OutputFormat::Parquet,
+            Some("key"),
+        );
+
+        let frame = ParquetReader::new(File::open(path).unwrap())
+            .finish()
+            .unwrap();
+
+        assert_eq!(
+            vec!["key", "labels", "score_sports", "score_weather"],
+            frame.get_column_names()
+        );
+        assert_eq!(2, frame.height());
+        let scores: Vec<Option<f32>> = frame
+            .column("score_sports")
+            .unwrap()
+            .f32()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(vec![Some(0.75), None], scores);
+        let labels: Vec<Option<&str>> = frame
+            .column("labels")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(vec![Some("weather|sports"), Some("")], labels);
+    }
+
+    #[test]
+    fn test_create_fails_in_a_missing_directory() {
+        assert!(PredictionWriter::create(
+            "does/not/exist/predictions.csv",
+            OutputFormat::Csv,
+            None,
+            class_names()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod test_pool {
+
+    use common::error::ServingError;
+    use common::pool::InferencePool;
+    use std::sync::mpsc;
+    use tokio::sync::oneshot;
+
+    /// A job signalling when it starts running, then blocking until released.
+    fn blocking_job() -> (
+        impl FnOnce() -> Result<(), ServingError> + Send + 'static,
+        oneshot::Receiver<()>,
+        mpsc::Sender<()>,
+    ) {
+        let (started, wait_started) = oneshot::channel();
+        let (release, wait_release) = mpsc::channel();
+        let job = move || {
+            started.send(()).unwrap();
+            wait_release.recv().unwrap();
+            Ok(())
+        };
+        (job, wait_started, release)
+    }
+
+    #[tokio::test]
+    async fn test_new_rejects_zero_concurrency() {
+        assert!(InferencePool::new(0, 4).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_returns_job_result() {
+        let pool = InferencePool::new(2, 2).unwrap();
+
+        let result = pool.run(|| Ok(21 * 2)).await.unwrap();
+
+        assert_eq!(42, result);
+    }
+
+    #[tokio::test]
+    async fn test_run_propagates_job_error() {
+        let pool = InferencePool::new(1, 0).unwrap();
+
+        let result: Result<(), ServingError> = pool
+            .run(|| Err(ServingError::Validation("Empty text".to_string())))
+            .await;
+
+        assert!(matches!(result, Err(ServingError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_run_rejects_when_saturated() {
+        let pool = InferencePool::new(1, 1).unwrap();
+        let (job, started, release) = blocking_job();
+
+        // Occupy the only running slot until released
+        let running = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(job).await }
+        });
+        started.await.unwrap();
+
+        // Fill the queue: polled once, the job is admitted and waits for the running slot
+        let mut queued = Box::pin(pool.run(|| Ok(())));
+        tokio::select! {
+            biased;
+            _ = &mut queued => panic!("The queued job ran while the running slot was taken"),
+            _ = std::future::ready(()) => {}
+        }
+
+        let rejected = pool.run(|| Ok(())).await;
+        assert!(matches!(rejected, Err(ServingError::Unavailable(_))));
+
+        release.send(()).unwrap();
+        running.await.unwrap().unwrap();
+        queued.await.unwrap();
+
+        // Slots are released once the jobs finish
+        assert!(pool.run(|| Ok(())).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_slots_are_held_until_the_job_finishes_when_the_caller_is_dropped() {
+        let pool = InferencePool::new(1, 0).unwrap();
+        let (job, started, release) = blocking_job();
+
+        let running = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(job).await }
+        });
+        started.await.unwrap();
+
+        // E.g. the client disconnected, the job keeps running on its blocking thread
+        running.abort();
+        assert!(running.await.unwrap_err().is_cancelled());
+
+        let rejected = pool.run(|| Ok(())).await;
+        assert!(matches!(rejected, Err(ServingError::Unavailable(_))));
+
+        release.send(()).unwrap();
+    }
+}
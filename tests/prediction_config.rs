@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod test_prediction_config {
+
+    use clap::Parser;
+    use common::prediction_config::{Args, InputFormat, OutputFormat};
+
+    fn parse(input: &str, output: &str) -> Args {
+        Args::try_parse_from(["predict", "--input", input, "--output", output]).unwrap()
+    }
+
+    #[test]
+    fn test_formats_are_guessed_from_the_extensions() {
+        assert_eq!(
+            InputFormat::Csv,
+            parse("a.csv", "b.csv").input_format().unwrap()
+        );
+        assert_eq!(
+            InputFormat::Jsonl,
+            parse("a.jsonl", "b.csv").input_format().unwrap()
+        );
+        assert_eq!(
+            InputFormat::Jsonl,
+            parse("a.ndjson", "b.csv").input_format().unwrap()
+        );
+
+        assert_eq!(
+            OutputFormat::Csv,
+            parse("a.csv", "b.csv").output_format().unwrap()
+        );
+        assert_eq!(
+            OutputFormat::Jsonl,
+            parse("a.csv", "b.jsonl").output_format().unwrap()
+        );
+        assert_eq!(
+            OutputFormat::Parquet,
+            parse("a.csv", "b.parquet").output_format().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_extensions_are_case_insensitive() {
+        let args = parse("data/A.CSV", "out/B.Parquet");
+
+        assert_eq!(InputFormat::Csv, args.input_format().unwrap());
+        assert_eq!(OutputFormat::Parquet, args.output_format().unwrap());
+    }
+
+    #[test]
+    fn test_unknown_extensions_are_rejected() {
+        let args = parse("texts.txt", "predictions");
+
+        assert!(args.input_format().is_err());
+        assert!(args.output_format().is_err());
+    }
+
+    #[test]
+    fn test_given_formats_win_over_the_extensions() {
+        let args = Args::try_parse_from([
+            "predict",
+            "--input",
+            "texts.txt",
+            "--output",
+            "predictions.csv",
+            "--input-format",
+            "jsonl",
+            "--output-format",
+            "parquet",
+        ])
+        .unwrap();
+
+        assert_eq!(InputFormat::Jsonl, args.input_format().unwrap());
+        assert_eq!(OutputFormat::Parquet, args.output_format().unwrap());
+    }
+
+    #[test]
+    fn test_defaults() {
+        let args = parse("a.csv", "b.csv");
+
+        assert_eq!("text", args.text_column);
+        assert_eq!(None, args.id_column);
+        assert_eq!(256, args.batch_size);
+        assert_eq!(None, args.threshold);
+        assert_eq!("model/model.bin", args.artifact_paths().model);
+    }
+}
@@ -0,0 +1,133 @@
+mod support;
+
+#[cfg(test)]
+mod test_registry {
+
+    use super::support::{serve_model, temp_dir, write_artifacts};
+    use common::error::ServingError;
+    use common::monitoring::ServingMetrics;
+    use common::pool::InferencePool;
+    use common::registry::{compare_versions, ModelRegistry};
+    use std::cmp::Ordering;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    fn load_registry(directory: &str) -> anyhow::Result<ModelRegistry> {
+        let pool = InferencePool::new(1, 1).unwrap();
+        let metrics = Arc::new(ServingMetrics::new().unwrap());
+        ModelRegistry::load(directory, |name, version, paths| {
+            serve_model(&name, version.as_deref(), paths, &pool, &metrics)
+        })
+    }
+
+    fn write_version(directory: &str, name: &str, version: &str, labels: &[&str]) {
+        let version_directory = Path::new(directory).join(name).join(version);
+        write_artifacts(&version_directory.to_string_lossy(), labels);
+    }
+
+    fn version_of(registry: &ModelRegistry, name: &str, version: Option<&str>) -> String {
+        registry
+            .get(name, version)
+            .unwrap()
+            .version
+            .clone()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_compare_versions_numerically() {
+        assert_eq!(Ordering::Less, compare_versions("2", "10"));
+        assert_eq!(Ordering::Greater, compare_versions("10", "9"));
+        assert_eq!(Ordering::Equal, compare_versions("3", "3"));
+    }
+
+    #[test]
+    fn test_compare_versions_with_v_prefix() {
+        assert_eq!(Ordering::Less, compare_versions("v2", "v10"));
+        assert_eq!(Ordering::Less, compare_versions("v2", "10"));
+        assert_eq!(Ordering::Greater, compare_versions("v11", "3"));
+    }
+
+    #[test]
+    fn test_compare_versions_not_numeric() {
+        assert_eq!(Ordering::Less, compare_versions("alpha", "beta"));
+        // Numeric versions come after the other ones
+        assert_eq!(Ordering::Less, compare_versions("latest", "1"));
+        assert_eq!(Ordering::Greater, compare_versions("v1", "beta"));
+    }
+
+    #[tokio::test]
+    async fn test_get_latest_and_explicit_versions() {
+        let directory = temp_dir("test_get_latest_and_explicit_versions");
+        write_version(&directory, "sections", "v2", &["sports", "weather"]);
+        write_version(&directory, "sections", "v10", &["politics", "sports"]);
+        write_version(&directory, "topics", "1", &["economy", "health"]);
+
+        let registry = load_registry(&directory).unwrap();
+
+        assert_eq!("v10", version_of(&registry, "sections", None));
+        assert_eq!("v2", version_of(&registry, "sections", Some("v2")));
+        assert_eq!("1", version_of(&registry, "topics", None));
+
+        let latest: Vec<(&str, &str)> = registry
+            .iter()
+            .filter(|(_, _, is_latest, _)| *is_latest)
+            .map(|(name, version, _, _)| (name, version))
+            .collect();
+        assert_eq!(vec![("sections", "v10"), ("topics", "1")], latest);
+    }
+
+    #[tokio::test]
+    async fn test_get_unknown_model_or_version() {
+        let directory = temp_dir("test_get_unknown_model_or_version");
+        write_version(&directory, "sections", "1", &["sports", "weather"]);
+
+        let registry = load_registry(&directory).unwrap();
+
+        assert!(matches!(
+            registry.get("topics", None),
+            Err(ServingError::ModelNotFound(_))
+        ));
+        assert!(matches!(
+            registry.get("sections", Some("2")),
+            Err(ServingError::ModelNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_load_empty_directory() {
+        let registry = load_registry(&temp_dir("test_load_empty_directory")).unwrap();
+
+        assert_eq!(0, registry.iter().count());
+    }
+
+    #[tokio::test]
+    async fn test_load_skips_models_without_versions() {
+        let directory = temp_dir("test_load_skips_models_without_versions");
+        fs::create_dir_all(Path::new(&directory).join("empty")).unwrap();
+        write_version(&directory, "sections", "1", &["sports", "weather"]);
+
+        let registry = load_registry(&directory).unwrap();
+
+        assert_eq!(1, registry.iter().count());
+        assert!(matches!(
+            registry.get("empty", None),
+            Err(ServingError::ModelNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_load_fails_on_a_version_without_artifacts() {
+        let directory = temp_dir("test_load_fails_on_a_version_without_artifacts");
+        write_version(&directory, "sections", "1", &["sports", "weather"]);
+        fs::create_dir_all(Path::new(&directory).join("sections").join("2")).unwrap();
+
+        assert!(load_registry(&directory).is_err());
+    }
+
+    #[test]
+    fn test_load_fails_on_a_missing_directory() {
+        assert!(load_registry("does/not/exist").is_err());
+    }
+}
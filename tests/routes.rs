@@ -0,0 +1,526 @@
+mod support;
+
+#[cfg(test)]
+mod test_routes {
+
+    use super::support::{serve_model, temp_dir, write_artifacts};
+    use common::monitoring::ServingMetrics;
+    use common::pool::InferencePool;
+    use common::registry::ModelRegistry;
+    use common::routes::{is_authorized, routes};
+    use serde_json::{json, Value};
+    use std::convert::Infallible;
+    use std::fs;
+    use std::sync::Arc;
+    use warp::http::StatusCode;
+    use warp::{Filter, Reply};
+
+    const MAX_BATCH_SIZE: usize = 3;
+
+    /// The routes of a server of an untrained model, its artifacts written in a directory named
+    /// after the test.
+    fn make_routes(
+        test_name: &str,
+    ) -> impl Filter<Extract = (impl Reply,), Error = Infallible> + Clone {
+        make_routes_with_token(test_name, None)
+    }
+
+    fn make_routes_with_token(
+        test_name: &str,
+        admin_token: Option<&str>,
+    ) -> impl Filter<Extract = (impl Reply,), Error = Infallible> + Clone {
+        let paths = write_artifacts(&temp_dir(test_name), &["sports", "weather"]);
+        let pool = InferencePool::new(2, 4).unwrap();
+        let metrics = Arc::new(ServingMetrics::new().unwrap());
+        let model = serve_model("default", None, paths, &pool, &metrics).unwrap();
+
+        routes(
+            Arc::new(model),
+            Arc::new(ModelRegistry::default()),
+            pool,
+            metrics,
+            admin_token.map(str::to_string),
+            MAX_BATCH_SIZE,
+        )
+    }
+
+    async fn post(
+        routes: &(impl Filter<Extract = (impl Reply,), Error = Infallible> + Clone + 'static),
+        path: &str,
+        body: &Value,
+    ) -> (StatusCode, Value) {
+        let response = warp::test::request()
+            .method("POST")
+            .path(path)
+            .json(body)
+            .reply(routes)
+            .await;
+        let body = serde_json::from_slice(response.body()).unwrap();
+        (response.status(), body)
+    }
+
+    /// Items whose texts have 1, 2 and 3 known tokens, so that the results can be told apart.
+    fn make_items() -> Value {
+        json!([
+            {"id": "first", "text": "storm"},
+            {"text": "storm hits"},
+            {"id": "3", "text": "storm hits coast"},
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_batch_results_follow_the_order_of_the_items() {
+        let routes = make_routes("test_batch_results_follow_the_order_of_the_items");
+
+        let (status, body) = post(
+            &routes,
+            "/predict/batch",
+            &json!({"items": make_items(), "top_k": 2}),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+        let results = body["results"].as_array().unwrap();
+        let n_tokens: Vec<u64> = results
+            .iter()
+            .map(|result| result["n_tokens"].as_u64().unwrap())
+            .collect();
+        assert_eq!(vec![1, 2, 3], n_tokens);
+        assert_eq!("default", body["metadata"]["model"]);
+
+        // Every result holds the prediction of its own text
+        for (item, result) in make_items().as_array().unwrap().iter().zip(results) {
+            let (_, single) = post(
+                &routes,
+                "/predict",
+                &json!({"text": item["text"], "top_k": 2}),
+            )
+            .await;
+            assert_eq!(single["predictions"], result["predictions"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_results_echo_the_ids() {
+        let routes = make_routes("test_batch_results_echo_the_ids");
+
+        for path in ["/predict/batch", "/v1/predict/batch"] {
+            let (status, body) = post(&routes, path, &json!({"items": make_items()})).await;
+
+            assert_eq!(StatusCode::OK, status);
+            let results = body["results"].as_array().unwrap();
+            assert_eq!(3, results.len());
+            assert_eq!(json!("first"), results[0]["id"]);
+            // Items without an id get a result without an id
+            assert!(results[1].get("id").is_none());
+            assert_eq!(json!("3"), results[2]["id"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_of_max_batch_size_is_accepted() {
+        let routes = make_routes("test_batch_of_max_batch_size_is_accepted");
+        let items: Vec<Value> = (0..MAX_BATCH_SIZE)
+            .map(|_| json!({"text": "storm"}))
+            .collect();
+
+        let (status, body) = post(&routes, "/predict/batch", &json!({ "items": items })).await;
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(MAX_BATCH_SIZE, body["results"].as_array().unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn test_batch_larger_than_max_batch_size_is_rejected() {
+        let routes = make_routes("test_batch_larger_than_max_batch_size_is_rejected");
+        let items: Vec<Value> = (0..=MAX_BATCH_SIZE)
+            .map(|_| json!({"text": "storm"}))
+            .collect();
+
+        for path in ["/predict/batch", "/v1/predict/batch"] {
+            let (status, body) = post(&routes, path, &json!({ "items": items })).await;
+
+            assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, status);
+            assert_eq!("batch_too_large", body["error"]["code"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_reports_the_invalid_items_and_classifies_the_others() {
+        let routes = make_routes("test_batch_reports_the_invalid_items_and_classifies_the_others");
+        let items = json!([
+            {"id": "a", "text": "  "},
+            {"id": "b", "text": "storm hits"},
+            {"id": "c", "text": "?!"},
+        ]);
+
+        for path in ["/predict/batch", "/v1/predict/batch"] {
+            let (status, body) = post(
+                &routes,
+                path,
+                &json!({"items": items, "top_k": 1, "include_all_scores": true}),
+            )
+            .await;
+
+            assert_eq!(StatusCode::OK, status);
+            let results = body["results"].as_array().unwrap();
+            let ids: Vec<&str> = results
+                .iter()
+                .map(|result| result["id"].as_str().unwrap())
+                .collect();
+            assert_eq!(vec!["a", "b", "c"], ids);
+
+            assert!(results[1].get("error").is_none());
+            assert!(!results[1]["predictions"].as_array().unwrap().is_empty());
+            assert_eq!("validation_error", results[0]["error"]["code"]);
+            assert_eq!(
+                "The text of item 0 is empty",
+                results[0]["error"]["message"]
+            );
+            assert_eq!("tokenization_error", results[2]["error"]["code"]);
+            assert_eq!(
+                "The text of item 2 does not contain any token",
+                results[2]["error"]["message"]
+            );
+            for invalid in [&results[0], &results[2]] {
+                assert_eq!(json!([]), invalid["predictions"]);
+                assert!(invalid.get("scores").is_none());
+            }
+        }
+
+        // The valid items are scored as if they were sent alone
+        let (_, body) = post(
+            &routes,
+            "/predict/batch",
+            &json!({"items": [{"text": "storm hits"}], "top_k": 1}),
+        )
+        .await;
+        let (_, mixed) = post(
+            &routes,
+            "/predict/batch",
+            &json!({"items": items, "top_k": 1}),
+        )
+        .await;
+        assert_eq!(
+            body["results"][0]["predictions"],
+            mixed["results"][1]["predictions"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_of_only_invalid_items() {
+        let routes = make_routes("test_batch_of_only_invalid_items");
+
+        let (status, body) = post(
+            &routes,
+            "/predict/batch",
+            &json!({"items": [{"text": ""}, {"text": "..."}]}),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(2, results.len());
+        assert!(results.iter().all(|result| result.get("error").is_some()));
+    }
+
+    /// The keys of a JSON object, sorted.
+    fn keys(value: &Value) -> Vec<&str> {
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    #[tokio::test]
+    async fn test_v1_predict_keeps_the_original_shape() {
+        let routes = make_routes("test_v1_predict_keeps_the_original_shape");
+
+        let (status, body) = post(
+            &routes,
+            "/v1/predict",
+            &json!({"text": "storm hits coast", "top_k": 2}),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+        // Neither metadata nor label objects, one single-entry map per class
+        assert_eq!(vec!["predictions"], keys(&body));
+        let predictions = body["predictions"].as_array().unwrap();
+        assert_eq!(2, predictions.len());
+        for prediction in predictions {
+            let (label, score) = prediction.as_object().unwrap().iter().next().unwrap();
+            assert_eq!(1, prediction.as_object().unwrap().len());
+            assert!(["sports", "weather"].contains(&label.as_str()));
+            assert!(score.is_f64());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_v1_predict_with_all_scores() {
+        let routes = make_routes("test_v1_predict_with_all_scores");
+
+        let (status, body) = post(
+            &routes,
+            "/v1/predict",
+            &json!({"text": "storm hits coast", "include_all_scores": true}),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(vec!["predictions", "scores"], keys(&body));
+        assert_eq!(vec!["sports", "weather"], keys(&body["scores"]));
+    }
+
+    #[tokio::test]
+    async fn test_v1_batch_results_keep_the_original_shape() {
+        let routes = make_routes("test_v1_batch_results_keep_the_original_shape");
+
+        let (status, body) = post(
+            &routes,
+            "/v1/predict/batch",
+            &json!({"items": [{"id": "a", "text": "storm"}], "top_k": 1}),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(vec!["results"], keys(&body));
+        let result = &body["results"][0];
+        assert_eq!(vec!["id", "predictions"], keys(result));
+        assert_eq!(1, result["predictions"][0].as_object().unwrap().len());
+    }
+
+    #[test]
+    fn test_is_authorized() {
+        assert!(is_authorized(Some("Bearer secret"), "secret"));
+        assert!(!is_authorized(Some("Bearer other"), "secret"));
+        assert!(!is_authorized(Some("Bearer secret2"), "secret"));
+        assert!(!is_authorized(Some("Bearer "), "secret"));
+        // The scheme is required
+        assert!(!is_authorized(Some("secret"), "secret"));
+        assert!(!is_authorized(Some("Basic secret"), "secret"));
+        assert!(!is_authorized(None, "secret"));
+    }
+
+    #[tokio::test]
+    async fn test_reload_requires_the_admin_token() {
+        let routes = make_routes_with_token("test_reload_requires_the_admin_token", Some("secret"));
+        let reload = |authorization: Option<&str>| {
+            let request = warp::test::request().method("POST").path("/admin/reload");
+            match authorization {
+                Some(authorization) => request.header("authorization", authorization),
+                None => request,
+            }
+        };
+
+        let response = reload(None).reply(&routes).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+        let response = reload(Some("Bearer wrong")).reply(&routes).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+        let response = reload(Some("Bearer secret")).reply(&routes).await;
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
+    /// The routes of a server of the untrained models `sections`, in versions 1 and 2, and
+    /// `topics`, in version v1, each version predicting its own labels.
+    fn make_registry_routes(
+        test_name: &str,
+    ) -> impl Filter<Extract = (impl Reply,), Error = Infallible> + Clone {
+        let directory = temp_dir(test_name);
+        let default_paths = write_artifacts(&format!("{}/default", directory), &["a", "b"]);
+        write_artifacts(
+            &format!("{}/models/sections/1", directory),
+            &["politics", "sports"],
+        );
+        write_artifacts(
+            &format!("{}/models/sections/2", directory),
+            &["economy", "health", "sports"],
+        );
+        write_artifacts(&format!("{}/models/topics/v1", directory), &["x", "y"]);
+        let pool = InferencePool::new(2, 4).unwrap();
+        let metrics = Arc::new(ServingMetrics::new().unwrap());
+        let default_model = serve_model("default", None, default_paths, &pool, &metrics).unwrap();
+        let registry =
+            ModelRegistry::load(&format!("{}/models", directory), |name, version, paths| {
+                serve_model(&name, version.as_deref(), paths, &pool, &metrics)
+            })
+            .unwrap();
+
+        routes(
+            Arc::new(default_model),
+            Arc::new(registry),
+            pool,
+            metrics,
+            None,
+            MAX_BATCH_SIZE,
+        )
+    }
+
+    /// The labels of a prediction response, sorted.
+    fn predicted_labels(body: &Value) -> Vec<&str> {
+        let mut labels: Vec<&str> = body["predictions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|prediction| prediction["label"].as_str().unwrap())
+            .collect();
+        labels.sort();
+        labels
+    }
+
+    #[tokio::test]
+    async fn test_list_models() {
+        let routes = make_registry_routes("test_list_models");
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/models")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(StatusCode::OK, response.status());
+        let body: Value = serde_json::from_slice(response.body()).unwrap();
+        let models: Vec<(&str, &str, bool)> = body["models"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|model| {
+                (
+                    model["name"].as_str().unwrap(),
+                    model["version"].as_str().unwrap(),
+                    model["latest"].as_bool().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("sections", "1", false),
+                ("sections", "2", true),
+                ("topics", "v1", true)
+            ],
+            models
+        );
+        assert_eq!(
+            json!(["economy", "health", "sports"]),
+            body["models"][1]["labels"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_predict_with_the_latest_version_of_a_model() {
+        let routes = make_registry_routes("test_predict_with_the_latest_version_of_a_model");
+
+        let (status, body) = post(
+            &routes,
+            "/models/sections/predict",
+            &json!({"text": "storm hits coast", "include_all_scores": true}),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("sections", body["metadata"]["model"]);
+        assert_eq!("2", body["metadata"]["version"]);
+        assert_eq!(vec!["economy", "health", "sports"], predicted_labels(&body));
+    }
+
+    #[tokio::test]
+    async fn test_predict_with_an_explicit_version_of_a_model() {
+        let routes = make_registry_routes("test_predict_with_an_explicit_version_of_a_model");
+
+        let (status, body) = post(
+            &routes,
+            "/models/sections/versions/1/predict",
+            &json!({"text": "storm hits coast", "include_all_scores": true}),
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("sections", body["metadata"]["model"]);
+        assert_eq!("1", body["metadata"]["version"]);
+        assert_eq!(vec!["politics", "sports"], predicted_labels(&body));
+    }
+
+    #[tokio::test]
+    async fn test_predict_with_an_unknown_model_or_version_is_not_found() {
+        let routes =
+            make_registry_routes("test_predict_with_an_unknown_model_or_version_is_not_found");
+
+        for path in [
+            "/models/unknown/predict",
+            "/models/unknown/versions/1/predict",
+            "/models/sections/versions/3/predict",
+            "/models/topics/versions/1/predict",
+        ] {
+            let (status, body) = post(&routes, path, &json!({"text": "storm"})).await;
+
+            assert_eq!(StatusCode::NOT_FOUND, status, "{}", path);
+            assert_eq!("model_not_found", body["error"]["code"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reload_reloads_every_model_and_reports_all_failures() {
+        let directory = temp_dir("test_reload_reloads_every_model_and_reports_all_failures");
+        let default_paths = write_artifacts(&format!("{}/default", directory), &["a", "b"]);
+        let broken_paths = write_artifacts(&format!("{}/models/broken/1", directory), &["a", "b"]);
+        write_artifacts(&format!("{}/models/sections/1", directory), &["a", "b"]);
+        let pool = InferencePool::new(2, 4).unwrap();
+        let metrics = Arc::new(ServingMetrics::new().unwrap());
+        let default_model = serve_model("default", None, default_paths, &pool, &metrics).unwrap();
+        let registry =
+            ModelRegistry::load(&format!("{}/models", directory), |name, version, paths| {
+                serve_model(&name, version.as_deref(), paths, &pool, &metrics)
+            })
+            .unwrap();
+        let sections = registry.get("sections", None).unwrap();
+        let routes = routes(
+            Arc::new(default_model),
+            Arc::new(registry),
+            pool.clone(),
+            metrics.clone(),
+            Some("secret".to_string()),
+            MAX_BATCH_SIZE,
+        );
+
+        // The broken model comes first, the models after it are reloaded anyway
+        fs::write(&broken_paths.model, b"not safetensors").unwrap();
+        write_artifacts(
+            &format!("{}/models/sections/1", directory),
+            &["a", "b", "c"],
+        );
+        let response = warp::test::request()
+            .method("POST")
+            .path("/admin/reload")
+            .header("authorization", "Bearer secret")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+        let body: Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!("reload_error", body["error"]["code"]);
+        let message = body["error"]["message"].as_str().unwrap();
+        assert!(message.contains("broken/1"));
+        assert!(!message.contains("sections/1"));
+        assert_eq!(3, sections.state.current().index_to_class.len());
+    }
+
+    #[tokio::test]
+    async fn test_reload_is_disabled_without_admin_token() {
+        let routes = make_routes("test_reload_is_disabled_without_admin_token");
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/admin/reload")
+            .header("authorization", "Bearer secret")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    }
+}
@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod test_serving_config {
+
+    use clap::Parser;
+    use common::serving_config::Args;
+    use common::ArtifactPaths;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::Duration;
+
+    #[test]
+    fn test_defaults() {
+        let args = Args::try_parse_from(["inference"]).unwrap();
+
+        assert_eq!(IpAddr::V4(Ipv4Addr::LOCALHOST), args.host);
+        assert_eq!(3030, args.port);
+        assert_eq!(None, args.model_dir);
+        assert_eq!(None, args.models_dir);
+        assert_eq!(256, args.max_batch_size);
+        assert_eq!(64, args.queue_depth);
+        assert_eq!(16, args.micro_batch_size);
+        assert_eq!(None, args.admin_token);
+        assert_eq!(None, args.reload_interval_secs);
+        assert!(args.max_concurrency() >= 1);
+    }
+
+    #[test]
+    fn test_flags() {
+        let args = Args::try_parse_from([
+            "inference",
+            "--host",
+            "0.0.0.0",
+            "--port",
+            "8080",
+            "--max-concurrency",
+            "3",
+            "--log-level",
+            "debug",
+        ])
+        .unwrap();
+
+        assert_eq!(IpAddr::V4(Ipv4Addr::UNSPECIFIED), args.host);
+        assert_eq!(8080, args.port);
+        assert_eq!(3, args.max_concurrency());
+        assert_eq!(Some("debug".to_string()), args.log_level);
+    }
+
+    #[test]
+    fn test_environment_variables() {
+        // Only read by this test, as the environment is shared by the tests running in parallel
+        std::env::set_var("INFERENCE_MICRO_BATCH_WAIT_MS", "7");
+
+        let args = Args::try_parse_from(["inference"]).unwrap();
+
+        assert_eq!(7, args.micro_batch_wait_ms);
+    }
+
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        assert!(Args::try_parse_from(["inference", "--host", "localhost:80"]).is_err());
+        assert!(Args::try_parse_from(["inference", "--port", "70000"]).is_err());
+        assert!(Args::try_parse_from(["inference", "--max-concurrency", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_zero_reload_interval_is_rejected() {
+        assert!(Args::try_parse_from(["inference", "--reload-interval-secs", "0"]).is_err());
+
+        let args = Args::try_parse_from(["inference", "--reload-interval-secs", "1"]).unwrap();
+        assert_eq!(Some(1), args.reload_interval_secs);
+    }
+
+    #[test]
+    fn test_batching_config() {
+        let args = Args::try_parse_from([
+            "inference",
+            "--max-concurrency",
+            "2",
+            "--queue-depth",
+            "6",
+            "--micro-batch-size",
+            "4",
+            "--micro-batch-wait-ms",
+            "5",
+        ])
+        .unwrap();
+
+        let batching = args.batching_config();
+
+        assert_eq!(4, batching.micro_batch_size);
+        assert_eq!(Duration::from_millis(5), batching.micro_batch_wait);
+        // Every running or queued slot of the pool can wait for a full batch
+        assert_eq!((2 + 6) * 4, batching.queue_capacity);
+    }
+
+    #[test]
+    fn test_artifact_paths_default_to_the_training_outputs() {
+        let args = Args::try_parse_from(["inference"]).unwrap();
+        let paths = args.artifact_paths();
+        let default = ArtifactPaths::default();
+
+        assert_eq!(default.model, paths.model);
+        assert_eq!(default.model_config, paths.model_config);
+        assert_eq!(default.vocabulary, paths.vocabulary);
+        assert_eq!(default.index_to_class, paths.index_to_class);
+    }
+
+    #[test]
+    fn test_artifact_paths_in_model_dir() {
+        let args = Args::try_parse_from(["inference", "--model-dir", "runs/3"]).unwrap();
+        let paths = args.artifact_paths();
+
+        assert_eq!("runs/3/model.bin", paths.model);
+        assert_eq!("runs/3/model_config.json", paths.model_config);
+        assert_eq!("runs/3/vocab.json", paths.vocabulary);
+        assert_eq!("runs/3/index_to_class.json", paths.index_to_class);
+    }
+}
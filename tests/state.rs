@@ -0,0 +1,117 @@
+mod support;
+
+#[cfg(test)]
+mod test_state {
+
+    use super::support::{temp_dir, write_artifacts};
+    use common::error::ServingError;
+    use common::monitoring::ServingMetrics;
+    use common::state::ModelState;
+    use common::ArtifactPaths;
+    use std::fs::{self, File};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    fn load_state(paths: &ArtifactPaths) -> Arc<ModelState> {
+        let metrics = Arc::new(ServingMetrics::new().unwrap());
+        Arc::new(ModelState::load(paths.clone(), metrics).unwrap())
+    }
+
+    /// Set the modification time of a file ahead of the current one, as a later training run
+    /// would, without depending on the resolution of the file system clock.
+    fn touch(path: &str, seconds_ahead: u64) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(seconds_ahead))
+            .unwrap();
+    }
+
+    fn n_labels(state: &ModelState) -> usize {
+        state.current().index_to_class.len()
+    }
+
+    #[tokio::test]
+    async fn test_reload_swaps_the_artifacts() {
+        let directory = temp_dir("test_reload_swaps_the_artifacts");
+        let paths = write_artifacts(&directory, &["sports", "weather"]);
+        let state = load_state(&paths);
+        let in_flight = state.current();
+
+        write_artifacts(&directory, &["politics", "sports", "weather"]);
+        state.reload().await.unwrap();
+
+        assert_eq!(3, n_labels(&state));
+        // A request in flight keeps the artifacts it started with
+        assert_eq!(2, in_flight.index_to_class.len());
+    }
+
+    #[tokio::test]
+    async fn test_reload_keeps_the_artifacts_on_failure() {
+        let directory = temp_dir("test_reload_keeps_the_artifacts_on_failure");
+        let paths = write_artifacts(&directory, &["sports", "weather"]);
+        let state = load_state(&paths);
+
+        fs::write(&paths.model, b"not safetensors").unwrap();
+        let result = state.reload().await;
+
+        assert!(matches!(result, Err(ServingError::Reload(_))));
+        assert_eq!(2, n_labels(&state));
+    }
+
+    #[tokio::test]
+    async fn test_reload_if_changed_waits_for_the_model_config() {
+        let directory = temp_dir("test_reload_if_changed_waits_for_the_model_config");
+        let paths = write_artifacts(&directory, &["sports", "weather"]);
+        let state = load_state(&paths);
+
+        assert!(!state.reload_if_changed().await.unwrap());
+
+        // A training run in progress has written the vocabulary but not the model config yet
+        touch(&paths.vocabulary, 60);
+        assert!(!state.reload_if_changed().await.unwrap());
+
+        write_artifacts(&directory, &["politics", "sports", "weather"]);
+        touch(&paths.model_config, 120);
+        assert!(state.reload_if_changed().await.unwrap());
+        assert_eq!(3, n_labels(&state));
+
+        assert!(!state.reload_if_changed().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_reload_if_changed_does_not_retry_a_broken_run() {
+        let directory = temp_dir("test_reload_if_changed_does_not_retry_a_broken_run");
+        let paths = write_artifacts(&directory, &["sports", "weather"]);
+        let state = load_state(&paths);
+
+        fs::write(&paths.model_config, b"{}").unwrap();
+        touch(&paths.model_config, 60);
+
+        assert!(state.reload_if_changed().await.is_err());
+        assert!(!state.reload_if_changed().await.unwrap());
+        assert_eq!(2, n_labels(&state));
+    }
+
+    #[tokio::test]
+    async fn test_watch_reloads_a_new_training_run() {
+        let directory = temp_dir("test_watch_reloads_a_new_training_run");
+        let paths = write_artifacts(&directory, &["sports", "weather"]);
+        let state = load_state(&paths);
+        let watcher = tokio::spawn(state.clone().watch(Duration::from_millis(10)));
+
+        write_artifacts(&directory, &["politics", "sports", "weather"]);
+        touch(&paths.model_config, 60);
+
+        let reloaded = tokio::time::timeout(Duration::from_secs(10), async {
+            while n_labels(&state) != 3 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await;
+        watcher.abort();
+
+        assert!(reloaded.is_ok());
+    }
+}
@@ -0,0 +1,93 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use candle_core::{DType, Device};
+use candle_nn::{VarBuilder, VarMap};
+use clap::Parser;
+use common::monitoring::ServingMetrics;
+use common::pool::InferencePool;
+use common::registry::ServedModel;
+use common::serving_config::Args;
+use common::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The texts the vocabulary of the test artifacts is built from.
+pub const CORPUS: [&str; 3] = [
+    "Storm hits the coast",
+    "Local team wins the final",
+    "Heavy rain expected tomorrow",
+];
+
+/// A fresh directory for the files of a test, named after it.
+pub fn temp_dir(name: &str) -> String {
+    let directory = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory.to_str().unwrap().to_string()
+}
+
+/// Write the artifacts of an untrained `mean_pooling` model predicting `labels` into
+/// `directory`, as a training run would.
+pub fn write_artifacts(directory: &str, labels: &[&str]) -> ArtifactPaths {
+    let paths = ArtifactPaths::in_directory(directory);
+    fs::create_dir_all(Path::new(directory)).unwrap();
+
+    let corpus: Vec<String> = CORPUS.iter().map(|text| text.to_string()).collect();
+    let vocabulary = VocabularyBuilder::new().build(&corpus, &DefaultTokenizer::default());
+    let model_config = ModelConfig {
+        vocab_size: vocabulary.n_indices(),
+        embedding_size: 4,
+        hidden_size: 5,
+        n_classes: labels.len(),
+        max_seq_len: 8,
+        ..ModelConfig::default()
+    };
+
+    let varmap = VarMap::new();
+    let vb = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+    build_classifier(&vb, &model_config).unwrap();
+    varmap.save(&paths.model).unwrap();
+
+    let index_to_class: HashMap<u32, String> = labels
+        .iter()
+        .enumerate()
+        .map(|(index, label)| (index as u32, label.to_string()))
+        .collect();
+    store_index_to_class_mapping(&index_to_class, &paths.index_to_class).unwrap();
+    store_vocabulary(&vocabulary, &paths.vocabulary).unwrap();
+    store_model_bundle(
+        &ModelBundle {
+            model: model_config,
+            tokenizer: vocabulary.tokenizer.clone(),
+            labels: labels.iter().map(|label| label.to_string()).collect(),
+            thresholds: Vec::new(),
+            training: TrainingMetadata::default(),
+        },
+        &paths.model_config,
+    )
+    .unwrap();
+
+    paths
+}
+
+/// Serve the model whose artifacts are at `paths`, with the default server options.
+pub fn serve_model(
+    name: &str,
+    version: Option<&str>,
+    paths: ArtifactPaths,
+    pool: &InferencePool,
+    metrics: &Arc<ServingMetrics>,
+) -> anyhow::Result<ServedModel> {
+    let args = Args::try_parse_from(["inference"]).unwrap();
+    ServedModel::load(
+        name.to_string(),
+        version.map(str::to_string),
+        paths,
+        pool,
+        metrics,
+        args.batching_config(),
+    )
+}
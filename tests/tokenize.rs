@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod test_tokenize {
+
+    use common::tokenize::*;
+
+    #[test]
+    fn test_tokenize_default() {
+        let tokenizer = DefaultTokenizer::default();
+
+        let actual_result = tokenizer.tokenize("Blizzard hits the South-West today!");
+        let expected_result: Vec<String> = vec![
+            "blizzard".to_string(),
+            "hits".to_string(),
+            "the".to_string(),
+            "southwest".to_string(),
+            "today".to_string(),
+        ];
+
+        assert_eq!(expected_result, actual_result);
+    }
+
+    #[test]
+    fn test_tokenize_unicode_punctuation() {
+        let tokenizer = DefaultTokenizer::default();
+
+        let actual_result = tokenizer.tokenize("“Storm” looms — again…");
+        let expected_result: Vec<String> = vec![
+            "storm".to_string(),
+            "looms".to_string(),
+            "again".to_string(),
+        ];
+
+        assert_eq!(expected_result, actual_result);
+    }
+
+    #[test]
+    fn test_tokenize_unicode_normalization() {
+        let tokenizer = DefaultTokenizer::default();
+
+        // Full-width letters and a decomposed "é" normalize to their canonical forms.
+        let actual_result = tokenizer.tokenize("Ｔｅｃｈ cafe\u{301}");
+        let expected_result: Vec<String> = vec!["tech".to_string(), "café".to_string()];
+
+        assert_eq!(expected_result, actual_result);
+    }
+
+    #[test]
+    fn test_tokenize_all_steps_disabled() {
+        let tokenizer = DefaultTokenizer::new(TokenizerConfig {
+            lowercase: false,
+            strip_punctuation: false,
+            normalize_unicode: false,
+        });
+
+        let actual_result = tokenizer.tokenize("  Today,  Rain! ");
+        let expected_result: Vec<String> = vec!["Today,".to_string(), "Rain!".to_string()];
+
+        assert_eq!(expected_result, actual_result);
+    }
+}
@@ -0,0 +1,257 @@
+mod support;
+
+#[cfg(test)]
+mod test_training_config {
+
+    use super::support::temp_dir;
+    use clap::Parser;
+    use common::embeddings::EmbeddingsFormat;
+    use common::training_config::*;
+    use common::{Architecture, Pooling};
+    use std::fs;
+
+    /// A config file setting a few values of some sections only.
+    const PARTIAL_CONFIG: &str = r#"
+[training]
+n_epochs = 5
+learning_rate = 0.01
+
+[model]
+architecture = "cnn"
+kernel_sizes = [3]
+
+[vocabulary]
+min_freq = 2
+
+[paths]
+model = "runs/1/model.bin"
+"#;
+
+    fn write_config(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    fn partial_config_args(name: &str) -> Args {
+        Args {
+            config: Some(write_config(name, PARTIAL_CONFIG)),
+            ..Args::default()
+        }
+    }
+
+    #[test]
+    fn test_from_file_fills_the_missing_values_with_the_defaults() {
+        let path = write_config("test_from_file_partial.toml", PARTIAL_CONFIG);
+
+        let config = Config::from_file(&path).unwrap();
+
+        assert_eq!(5, config.training.n_epochs);
+        assert_eq!(0.01, config.training.learning_rate);
+        assert_eq!(
+            TrainConfig::default().batch_size,
+            config.training.batch_size
+        );
+        assert_eq!(Architecture::Cnn, config.model.architecture);
+        assert_eq!(vec![3], config.model.kernel_sizes);
+        assert_eq!(Pooling::default(), config.model.pooling);
+        assert_eq!(2, config.vocabulary.min_freq);
+        assert!(config.tokenizer.lowercase);
+        assert_eq!(None, config.embeddings.path);
+        assert_eq!("runs/1/model.bin", config.paths.model);
+        assert_eq!("data/train.csv", config.paths.train_data);
+    }
+
+    #[test]
+    fn test_from_file_reads_the_example_config() {
+        let config = Config::from_file("config/training.toml").unwrap();
+
+        assert_eq!(Architecture::MeanPooling, config.model.architecture);
+        assert_eq!("model/model.bin", config.paths.model);
+    }
+
+    #[test]
+    fn test_from_file_rejects_unknown_values() {
+        let path = write_config(
+            "test_from_file_invalid.toml",
+            "[model]\narchitecture = \"rnn\"\n",
+        );
+
+        assert!(Config::from_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_from_file_fails_on_missing_file() {
+        assert!(Config::from_file("does/not/exist.toml").is_err());
+    }
+
+    #[test]
+    fn test_from_args_without_config_file_uses_the_defaults() {
+        let config = Config::from_args(Args::default()).unwrap();
+        let default = Config::default();
+
+        assert_eq!(default.training.n_epochs, config.training.n_epochs);
+        assert_eq!(default.model.architecture, config.model.architecture);
+        assert_eq!(default.paths.model, config.paths.model);
+    }
+
+    #[test]
+    fn test_from_args_keeps_the_file_values_without_override() {
+        let config = Config::from_args(partial_config_args("test_from_args_keeps.toml")).unwrap();
+
+        assert_eq!(5, config.training.n_epochs);
+        assert_eq!(Architecture::Cnn, config.model.architecture);
+        assert_eq!(2, config.vocabulary.min_freq);
+    }
+
+    #[test]
+    fn test_from_args_overrides_the_training_section() {
+        let args = Args {
+            n_epochs: Some(7),
+            seed: Some(1),
+            validation_fraction: Some(0.25),
+            calibrate_thresholds: Some(false),
+            ..partial_config_args("test_from_args_training.toml")
+        };
+
+        let config = Config::from_args(args).unwrap();
+
+        assert_eq!(7, config.training.n_epochs);
+        assert_eq!(1, config.training.seed);
+        assert_eq!(0.25, config.training.validation_fraction);
+        assert!(!config.training.calibrate_thresholds);
+        // Not overridden
+        assert_eq!(0.01, config.training.learning_rate);
+    }
+
+    #[test]
+    fn test_from_args_overrides_the_model_section() {
+        let args = Args {
+            architecture: Some(Architecture::Lstm),
+            pooling: Some(Pooling::Max),
+            kernel_sizes: Some(vec![2, 4]),
+            ..partial_config_args("test_from_args_model.toml")
+        };
+
+        let config = Config::from_args(args).unwrap();
+
+        assert_eq!(Architecture::Lstm, config.model.architecture);
+        assert_eq!(Pooling::Max, config.model.pooling);
+        assert_eq!(vec![2, 4], config.model.kernel_sizes);
+    }
+
+    #[test]
+    fn test_from_args_overrides_the_tokenizer_section() {
+        let args = Args {
+            lowercase: Some(false),
+            ..partial_config_args("test_from_args_tokenizer.toml")
+        };
+
+        let config = Config::from_args(args).unwrap();
+
+        assert!(!config.tokenizer.lowercase);
+        assert!(config.tokenizer.strip_punctuation);
+    }
+
+    #[test]
+    fn test_from_args_overrides_the_vocabulary_section() {
+        let args = Args {
+            min_freq: Some(3),
+            max_vocab_size: Some(100),
+            stop_words: Some(vec!["the".to_string()]),
+            ..partial_config_args("test_from_args_vocabulary.toml")
+        };
+
+        let config = Config::from_args(args).unwrap();
+
+        assert_eq!(3, config.vocabulary.min_freq);
+        assert_eq!(Some(100), config.vocabulary.max_size);
+        assert_eq!(vec!["the".to_string()], config.vocabulary.stop_words);
+    }
+
+    #[test]
+    fn test_from_args_overrides_the_embeddings_section() {
+        let args = Args {
+            pretrained_embeddings: Some("vectors.bin".to_string()),
+            embeddings_format: Some(EmbeddingsFormat::Text),
+            freeze_embeddings: Some(true),
+            ..partial_config_args("test_from_args_embeddings.toml")
+        };
+
+        let config = Config::from_args(args).unwrap();
+
+        assert_eq!(Some("vectors.bin".to_string()), config.embeddings.path);
+        assert_eq!(
+            EmbeddingsFormat::Text,
+            config.embeddings.format("vectors.bin")
+        );
+        assert!(config.embeddings.freeze);
+    }
+
+    #[test]
+    fn test_from_args_overrides_the_paths_section() {
+        let args = Args {
+            train_data: Some("other/train.csv".to_string()),
+            model_path: Some("runs/2/model.bin".to_string()),
+            ..partial_config_args("test_from_args_paths.toml")
+        };
+
+        let config = Config::from_args(args).unwrap();
+
+        assert_eq!("other/train.csv", config.paths.train_data);
+        assert_eq!("runs/2/model.bin", config.paths.model);
+        assert_eq!("data/test.csv", config.paths.test_data);
+    }
+
+    #[test]
+    fn test_from_args_parses_the_command_line() {
+        let args = Args::try_parse_from([
+            "training",
+            "--n-epochs",
+            "3",
+            "--kernel-sizes",
+            "2,3",
+            "--stop-words",
+            "a,the",
+        ])
+        .unwrap();
+
+        let config = Config::from_args(args).unwrap();
+
+        assert_eq!(3, config.training.n_epochs);
+        assert_eq!(vec![2, 3], config.model.kernel_sizes);
+        assert_eq!(vec!["a", "the"], config.vocabulary.stop_words);
+    }
+
+    #[test]
+    fn test_from_args_fails_on_missing_config_file() {
+        let args = Args {
+            config: Some("does/not/exist.toml".to_string()),
+            ..Args::default()
+        };
+
+        assert!(Config::from_args(args).is_err());
+    }
+
+    #[test]
+    fn test_store_effective_writes_next_to_the_model() {
+        let directory = temp_dir("test_store_effective_writes_next_to_the_model");
+        let args = Args {
+            model_path: Some(format!("{}/model.bin", directory)),
+            n_epochs: Some(9),
+            ..partial_config_args("test_store_effective.toml")
+        };
+        let config = Config::from_args(args).unwrap();
+
+        let path = config.store_effective().unwrap();
+        let stored = Config::from_file(&path).unwrap();
+
+        assert_eq!(
+            format!("{}/{}", directory, EFFECTIVE_CONFIG_FILE_NAME),
+            path
+        );
+        assert_eq!(9, stored.training.n_epochs);
+        assert_eq!(Architecture::Cnn, stored.model.architecture);
+        assert_eq!(config.paths.model, stored.paths.model);
+    }
+}
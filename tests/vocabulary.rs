@@ -1,14 +1,15 @@
 #[cfg(test)]
 mod encode {
 
+    use common::tokenize::*;
     use common::vocabulary::*;
 
     #[test]
     fn test_make_vocabulary_single_sentence() {
         let corpus = vec!["Hello, world!".to_string()];
-        let vocabulary = make_vocabulary(&corpus);
+        let vocabulary = make_vocabulary(&corpus, &DefaultTokenizer::default());
 
-        let expected_words_in_vocabulary = vec!["Hello".to_string(), "world".to_string()];
+        let expected_words_in_vocabulary = vec!["hello".to_string(), "world".to_string()];
         println!("{:?}", vocabulary);
         for word in expected_words_in_vocabulary {
             assert!(vocabulary.contains(&word));
@@ -18,16 +19,70 @@ mod encode {
     #[test]
     fn test_make_vocabulary_multiple_sentences() {
         let corpus = vec!["This is a test.".to_string(), "Another test.".to_string()];
-        let vocabulary = make_vocabulary(&corpus);
+        let vocabulary = make_vocabulary(&corpus, &DefaultTokenizer::default());
         let expected_words_in_vocabulary: Vec<String> = vec![
-            "This".to_string(),
+            "this".to_string(),
             "is".to_string(),
             "a".to_string(),
             "test".to_string(),
-            "Another".to_string(),
+            "another".to_string(),
         ];
         for word in expected_words_in_vocabulary {
             assert!(vocabulary.contains(&word));
         }
     }
+
+    #[test]
+    fn test_make_vocabulary_preserves_case_when_configured() {
+        let corpus = vec!["Hello, world!".to_string()];
+        let tokenizer = DefaultTokenizer::new(TokenizerConfig {
+            lowercase: false,
+            ..TokenizerConfig::default()
+        });
+        let vocabulary = make_vocabulary(&corpus, &tokenizer);
+
+        assert!(vocabulary.contains(&"Hello".to_string()));
+        assert!(!vocabulary.contains(&"hello".to_string()));
+    }
+
+    #[test]
+    fn test_vocabulary_builder_sorts_by_frequency_then_word() {
+        let corpus = vec![
+            "storm hits coast".to_string(),
+            "storm delays match".to_string(),
+            "match ends".to_string(),
+        ];
+        let vocabulary = VocabularyBuilder::new().build(&corpus, &DefaultTokenizer::default());
+
+        let expected_vocabulary: Vec<String> = vec![
+            "match".to_string(),
+            "storm".to_string(),
+            "coast".to_string(),
+            "delays".to_string(),
+            "ends".to_string(),
+            "hits".to_string(),
+        ];
+        assert_eq!(expected_vocabulary, vocabulary.vocabulary);
+        assert_eq!(Some(&2), vocabulary.counts.get("storm"));
+        assert_eq!(Some(&1), vocabulary.counts.get("coast"));
+    }
+
+    #[test]
+    fn test_vocabulary_builder_prunes_tokens() {
+        let corpus = vec![
+            "the storm hits the coast".to_string(),
+            "the storm delays the match".to_string(),
+            "match ends".to_string(),
+        ];
+        let vocabulary = VocabularyBuilder::new()
+            .min_freq(2)
+            .max_size(1)
+            .stop_words(["the"])
+            .build(&corpus, &DefaultTokenizer::default());
+
+        assert_eq!(vec!["match".to_string()], vocabulary.vocabulary);
+        assert_eq!(1, vocabulary.counts.len());
+        // <PAD>, <UNK> and the single word
+        assert_eq!(3, vocabulary.n_indices());
+    }
 }